@@ -0,0 +1,44 @@
+#[macro_use] extern crate criterion;
+extern crate mount_arelle;
+
+use criterion::Criterion;
+
+use mount_arelle::dungeon::{Dungeon, DungeonParams, Rooms};
+use mount_arelle::texture::Textures;
+
+const SIZES: &[&str] = &["small", "medium", "large"];
+
+// The default params with the other dungeon sizes weighted out, so every
+// seed gives a dungeon of `size`.
+fn params_for(size: &str) -> DungeonParams {
+    let mut params = DungeonParams::default();
+    if size != "small" {
+        params.small.weight = 0;
+    }
+    if size != "medium" {
+        params.medium.weight = 0;
+    }
+    if size != "large" {
+        params.large.weight = 0;
+    }
+
+    params
+}
+
+fn generate(c: &mut Criterion) {
+    c.bench_function_over_inputs("generate", |b, &size| {
+        let params = params_for(size);
+        b.iter(|| Dungeon::with_generator("bench", Textures::builtin(), &Rooms, &params))
+    }, SIZES.to_vec());
+}
+
+// Only builds the map image; writing it out would mostly time the disk.
+fn render_image(c: &mut Criterion) {
+    c.bench_function_over_inputs("render_image", |b, &size| {
+        let dungeon = Dungeon::with_generator("bench", Textures::builtin(), &Rooms, &params_for(size));
+        b.iter(|| dungeon.grid.image())
+    }, SIZES.to_vec());
+}
+
+criterion_group!(benches, generate, render_image);
+criterion_main!(benches);
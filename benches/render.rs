@@ -0,0 +1,41 @@
+#[macro_use] extern crate criterion;
+extern crate mount_arelle;
+
+use criterion::Criterion;
+
+use mount_arelle::dungeon::Dungeon;
+use mount_arelle::player::Player;
+use mount_arelle::renderer::Renderer;
+
+// A fixed seed and viewpoint so runs can be compared
+fn scene() -> (Dungeon, Player) {
+    let dungeon = Dungeon::new_from_seed("bench");
+    let (px, py) = dungeon.player_spawn();
+    let mut player = Player::new(px, py);
+    player.rotate(45.0);
+
+    (dungeon, player)
+}
+
+fn render(c: &mut Criterion) {
+    let (dungeon, player) = scene();
+    let renderer = Renderer::new();
+
+    // Widths of a 16:10 view
+    c.bench_function_over_inputs("render", move |b, &width| {
+        b.iter(|| renderer.render(&dungeon, &player, width, width * 5 / 8))
+    }, vec![320, 640, 1280]);
+}
+
+fn render_one_thread(c: &mut Criterion) {
+    let (dungeon, player) = scene();
+    let mut renderer = Renderer::new();
+    renderer.parallel = false;
+
+    c.bench_function("render one thread/640", move |b| {
+        b.iter(|| renderer.render(&dungeon, &player, 640, 400))
+    });
+}
+
+criterion_group!(benches, render, render_one_thread);
+criterion_main!(benches);
@@ -1,11 +1,20 @@
 use std::env::args;
 
 extern crate mount_arelle;
-use mount_arelle::dungeon::Dungeon;
+use mount_arelle::dungeon::{self, Dungeon, DungeonParams, GENERATORS};
+use mount_arelle::texture::Textures;
 
+// Usage: render_image <seed> [generator]
 fn main() {
     let seed = args().nth(1).expect("No seed specified");
-    let dungeon = Dungeon::new_from_seed(&seed);
+    let name = args().nth(2).unwrap_or(GENERATORS[0].to_string());
+
+    let generator = dungeon::generator(&name).unwrap_or_else(|| {
+        panic!("Unknown generator {}, expected one of: {}", name, GENERATORS.join(", "))
+    });
+    let params = DungeonParams::load_or_default("resources/dungeon.toml")
+        .unwrap_or_else(|err| panic!("Couldn't load resources/dungeon.toml: {}", err));
+    let dungeon = Dungeon::with_generator(&seed, Textures::builtin(), &*generator, &params);
 
     let _ = dungeon.render_grid(format!("{}.png", seed));
 }
@@ -0,0 +1,30 @@
+use std::env::args;
+
+extern crate picto;
+use picto::write;
+
+extern crate mount_arelle;
+use mount_arelle::dungeon::{self, Dungeon, DungeonParams, GENERATORS};
+use mount_arelle::player::Player;
+use mount_arelle::renderer::render_view;
+use mount_arelle::texture::Textures;
+
+// Usage: render_view <seed> [width] [height] [generator]
+fn main() {
+    let seed = args().nth(1).expect("No seed specified");
+    let width = args().nth(2).map_or(800, |w| w.parse().expect("Invalid width"));
+    let height = args().nth(3).map_or(600, |h| h.parse().expect("Invalid height"));
+    let name = args().nth(4).unwrap_or(GENERATORS[0].to_string());
+
+    let generator = dungeon::generator(&name).unwrap_or_else(|| {
+        panic!("Unknown generator {}, expected one of: {}", name, GENERATORS.join(", "))
+    });
+    let params = DungeonParams::load_or_default("resources/dungeon.toml")
+        .unwrap_or_else(|err| panic!("Couldn't load resources/dungeon.toml: {}", err));
+    let dungeon = Dungeon::with_generator(&seed, Textures::builtin(), &*generator, &params);
+    let (px, py) = dungeon.player_spawn();
+    let player = Player::new(px, py);
+
+    let image = render_view(&dungeon, &player, width, height);
+    let _ = write::to_path(format!("{}_view.png", seed), &image);
+}
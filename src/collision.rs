@@ -0,0 +1,164 @@
+use grid::Grid;
+
+use std::cmp::Ordering;
+
+/// Moves a circle of `radius` centered on (`x`, `y`) by (`dx`, `dy`) and
+/// returns where it ends up.
+///
+/// Solid tiles and everything outside the grid push the circle back out, so
+/// running into a wall at an angle slides along it instead of stopping dead.
+/// Long moves are split into steps shorter than the radius so the circle
+/// can't tunnel through thin walls.
+pub fn slide(grid: &Grid, x: f64, y: f64, dx: f64, dy: f64, radius: f64) -> (f64, f64) {
+    let steps = (dx.abs().max(dy.abs()) / (radius / 2.0)).ceil().max(1.0) as u32;
+    let (step_x, step_y) = (dx / steps as f64, dy / steps as f64);
+
+    let (mut x, mut y) = (x, y);
+    for _ in 0..steps {
+        x += step_x;
+        y += step_y;
+
+        // Pushing out of one tile can push into its neighbour
+        for _ in 0..2 {
+            let (px, py) = push_out(grid, x, y, radius);
+            x = px;
+            y = py;
+        }
+    }
+
+    (x, y)
+}
+
+/// Whether a circle overlaps any solid tile.
+pub fn overlaps(grid: &Grid, x: f64, y: f64, radius: f64) -> bool {
+    push_out(grid, x, y, radius) != (x, y)
+}
+
+fn push_out(grid: &Grid, x: f64, y: f64, radius: f64) -> (f64, f64) {
+    let (mut x, mut y) = (x, y);
+
+    let x1 = (x - radius).floor() as i64;
+    let x2 = (x + radius).floor() as i64;
+    let y1 = (y - radius).floor() as i64;
+    let y2 = (y + radius).floor() as i64;
+
+    let mut solid = Vec::new();
+    for cx in x1..x2 + 1 {
+        for cy in y1..y2 + 1 {
+            if is_solid(grid, cx, cy) {
+                solid.push((cx, cy));
+            }
+        }
+    }
+
+    // Nearest tiles first, so a flat wall is handled before the corner of
+    // the next tile along can shove the circle sideways
+    solid.sort_by(|a, b| {
+        let da = nearest(x, y, a.0, a.1);
+        let db = nearest(x, y, b.0, b.1);
+        da.2.partial_cmp(&db.2).unwrap_or(Ordering::Equal)
+    });
+
+    for (cx, cy) in solid {
+        let (ox, oy, dist) = nearest(x, y, cx, cy);
+        if dist < radius && dist > 0.0 {
+            x += ox / dist * (radius - dist);
+            y += oy / dist * (radius - dist);
+        }
+    }
+
+    (x, y)
+}
+
+// Offset from the nearest point of a tile to (x, y), and its length.
+fn nearest(x: f64, y: f64, cx: i64, cy: i64) -> (f64, f64, f64) {
+    let ox = x - x.max(cx as f64).min(cx as f64 + 1.0);
+    let oy = y - y.max(cy as f64).min(cy as f64 + 1.0);
+    (ox, oy, (ox.powi(2) + oy.powi(2)).sqrt())
+}
+
+fn is_solid(grid: &Grid, x: i64, y: i64) -> bool {
+    if x < 0 || y < 0 {
+        return true;
+    }
+
+    grid.get(x as u32, y as u32).map_or(true, |tile| tile.is_solid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::Material;
+
+    fn grid(rows: &[&str]) -> Grid {
+        Grid::from_rows(rows, Material::new(0, 0, 0.35, 1.0))
+    }
+
+    #[test]
+    fn moves_freely_in_the_open() {
+        let grid = grid(&[
+            "#####",
+            "#...#",
+            "#...#",
+            "#...#",
+            "#####",
+        ]);
+
+        let (x, y) = slide(&grid, 2.5, 2.5, 0.3, -0.2, 0.25);
+        assert!((x - 2.8).abs() < 1e-9);
+        assert!((y - 2.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stops_a_radius_from_walls() {
+        let grid = grid(&[
+            "#####",
+            "#...#",
+            "#####",
+        ]);
+
+        let (x, y) = slide(&grid, 2.5, 1.5, 2.0, 0.0, 0.25);
+        assert!((x - 3.75).abs() < 1e-9);
+        assert_eq!(y, 1.5);
+    }
+
+    #[test]
+    fn slides_along_walls() {
+        let grid = grid(&[
+            "#######",
+            "#.....#",
+            "#######",
+        ]);
+
+        // Pushing diagonally into the top wall still moves along it
+        let (x, y) = slide(&grid, 1.5, 1.5, 1.0, -1.0, 0.25);
+        assert!((x - 2.5).abs() < 1e-9);
+        assert!((y - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_squeezing_between_corners() {
+        let grid = grid(&[
+            "####",
+            "#.##",
+            "##.#",
+            "####",
+        ]);
+
+        let (x, y) = slide(&grid, 1.5, 1.5, 1.0, 1.0, 0.25);
+        assert!(x < 2.0 && y < 2.0);
+        assert!(!overlaps(&grid, x, y, 0.25));
+    }
+
+    #[test]
+    fn no_tunnelling_through_thin_walls() {
+        let grid = grid(&[
+            "#####",
+            "#.#.#",
+            "#####",
+        ]);
+
+        let (x, _) = slide(&grid, 1.5, 1.5, 5.0, 0.0, 0.25);
+        assert!(x < 2.0);
+    }
+}
@@ -0,0 +1,134 @@
+use toml;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use game::Mode;
+use input::{Bindings, Gamepad};
+use settings::Settings;
+
+/// The user's settings file, `config.toml` in the user config directory.
+///
+/// ```toml
+/// mode = "turn_based"
+///
+/// [settings]
+/// fov = 90.0
+/// mouse_sensitivity = 0.3
+/// render_width = 320
+/// render_height = 200
+/// player_radius = 0.25
+///
+/// [bindings]
+/// forward = ["W", "Up"]
+/// use = ["E", "Mouse Right"]
+///
+/// [gamepad]
+/// deadzone = 0.2
+/// look_speed = 180.0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Vec<String>>, // Action name to key names
+    #[serde(default)]
+    pub gamepad: GamepadConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub deadzone: f64,
+    pub look_speed: f64,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        Config::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Config> {
+        toml::from_str(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        File::create(path)?.write_all(text.as_bytes())
+    }
+
+    /// The key bindings, with the defaults for any action the file leaves
+    /// out.
+    pub fn bindings(&self) -> io::Result<Bindings> {
+        Bindings::from_names(&self.bindings)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn set_bindings(&mut self, bindings: &Bindings) {
+        self.bindings = bindings.to_names();
+    }
+
+    /// The gamepad settings, clamped to sensible ranges.
+    pub fn gamepad(&self) -> Gamepad {
+        Gamepad::new(self.gamepad.deadzone, self.gamepad.look_speed).clamped()
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        let gamepad = Gamepad::default();
+        GamepadConfig {
+            deadzone: gamepad.deadzone,
+            look_speed: gamepad.look_speed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ggez::event::Keycode;
+    use input::{Action, Input, MAX_DEADZONE};
+    use temp_file::TempFile;
+
+    #[test]
+    fn parse_bindings() {
+        let config = Config::parse("[bindings]\nforward = [\"Up\"]\n").unwrap();
+        let bindings = config.bindings().unwrap();
+        assert_eq!(bindings.action(Input::Key(Keycode::Up)), Some(Action::Forward));
+        assert_eq!(bindings.action(Input::Key(Keycode::S)), Some(Action::Back));
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        let config = Config::parse("[gamepad]\nlook_speed = 90.0\n").unwrap();
+        assert_eq!(config.gamepad(), Gamepad::new(0.2, 90.0));
+        let config = Config::parse("[gamepad]\ndeadzone = 1.0\nlook_speed = -10.0\n").unwrap();
+        assert_eq!(config.gamepad(), Gamepad::new(MAX_DEADZONE, 0.0));
+        assert!(Config::parse("[bindings]\nforward = \"Up\"\n").is_err());
+
+        let config = Config::parse("mode = \"turn_based\"\n").unwrap();
+        assert_eq!(config.mode, Mode::TurnBased);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut config = Config::default();
+        config.mode = Mode::TurnBased;
+        config.settings.fov = 90.0;
+        config.set_bindings(&Bindings::default());
+
+        let file = TempFile::new("config_test.toml");
+        config.save(file.path()).unwrap();
+        assert_eq!(Config::load(file.path()).unwrap(), config);
+    }
+}
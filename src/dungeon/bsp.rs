@@ -0,0 +1,106 @@
+use rand::Rng;
+use rand::isaac::IsaacRng;
+
+use std::sync::Arc;
+
+use grid::{Grid, Tile};
+use light::LightMap;
+use texture::Textures;
+
+use super::*;
+
+// Areas are never split into pieces narrower than this
+const MIN_LEAF: u32 = 12;
+const MIN_ROOM: u32 = 5;
+
+/// Binary space partitioning: the map is cut in two again and again, each
+/// piece gets a room, and the two halves of every cut are joined. Rooms
+/// never overlap and spread evenly over the map.
+pub struct Bsp;
+
+impl Generator for Bsp {
+    fn generate(&self, rng: &mut IsaacRng, params: &DungeonParams, textures: Arc<Textures>) -> Dungeon {
+        let (_, dw, dh) = pick_size(params, rng);
+        let theme = rng.choose(THEMES).unwrap();
+
+        let mut grid = Grid::with_textures(dw, dh, textures.clone());
+        grid.fill(Tile::Wall(texture(&textures, theme.walls[0])));
+
+        let mut rooms = Vec::new();
+        let mut edges = Vec::new();
+        split(Room::new(0, 0, dw, dh), &mut rooms, &mut edges, rng);
+
+        build_rooms(&mut grid, &rooms, theme, rng);
+        let corridor = theme.corridor(&textures);
+        for &(a, b) in &edges {
+            join(&mut grid, corridor, params.corridor_width, &rooms[a], &rooms[b], rng);
+        }
+
+        connect(&mut grid, corridor);
+        debug_assert!(grid.is_connected());
+        place_doors(&mut grid, &rooms, texture(&textures, "door_wood"), rng);
+
+        let (px, py) = rng.choose(&rooms).unwrap().center();
+        let (lights, sprites) = furnish(&rooms, (px, py), &textures, rng);
+        let light_map = LightMap::new(&grid, &lights);
+
+        Dungeon {
+            grid: grid,
+            lights: lights,
+            sprites: sprites,
+            room_graph: RoomGraph {
+                rooms: rooms,
+                edges: edges,
+            },
+            light_map: light_map,
+            player_spawn: (px, py),
+        }
+    }
+}
+
+// Splits `area` until the pieces are too small to cut again and puts a room
+// in each. Returns the index of one of the rooms inside `area`, for the cut
+// above to join to.
+fn split<R: Rng>(area: Room, rooms: &mut Vec<Room>, edges: &mut Vec<(usize, usize)>, rng: &mut R) -> usize {
+    let can_cut_x = area.w >= MIN_LEAF * 2;
+    let can_cut_y = area.h >= MIN_LEAF * 2;
+
+    let cut_x = match (can_cut_x, can_cut_y) {
+        (false, false) => {
+            // Leave at least a tile of wall all round so neighbouring rooms
+            // don't share walls
+            let (max_w, max_h) = (area.w - 2, area.h - 2);
+            let rw = rng.gen_range(MIN_ROOM.min(max_w), max_w + 1);
+            let rh = rng.gen_range(MIN_ROOM.min(max_h), max_h + 1);
+            let rx = area.x + rng.gen_range(1, area.w - rw);
+            let ry = area.y + rng.gen_range(1, area.h - rh);
+
+            rooms.push(Room::new(rx, ry, rw, rh));
+            return rooms.len() - 1;
+        },
+        (true, false) => true,
+        (false, true) => false,
+        // Cut long areas across so the pieces stay roughly square
+        (true, true) => if area.w > area.h * 5 / 4 {
+            true
+        } else if area.h > area.w * 5 / 4 {
+            false
+        } else {
+            rng.gen()
+        },
+    };
+
+    let (a, b) = if cut_x {
+        let cut = rng.gen_range(MIN_LEAF, area.w - MIN_LEAF + 1);
+        (Room::new(area.x, area.y, cut, area.h), Room::new(area.x + cut, area.y, area.w - cut, area.h))
+    } else {
+        let cut = rng.gen_range(MIN_LEAF, area.h - MIN_LEAF + 1);
+        (Room::new(area.x, area.y, area.w, cut), Room::new(area.x, area.y + cut, area.w, area.h - cut))
+    };
+
+    let first = split(a, rooms, edges, rng);
+    let second = split(b, rooms, edges, rng);
+    edges.push((first.min(second), first.max(second)));
+
+    if rng.gen() { first } else { second }
+}
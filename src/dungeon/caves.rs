@@ -0,0 +1,138 @@
+use rand::Rng;
+use rand::isaac::IsaacRng;
+
+use std::sync::Arc;
+
+use grid::{Grid, Tile};
+use light::LightMap;
+use texture::Textures;
+
+use super::*;
+
+/// Caverns for The Mines, grown with a cellular automaton: the map starts
+/// as random noise and each pass turns a tile to rock if most of the tiles
+/// around it are rock. Caverns too small to bother with are filled in and
+/// the rest are joined by tunnels. Tuned by `DungeonParams::caves`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caves;
+
+impl Generator for Caves {
+    fn generate(&self, rng: &mut IsaacRng, params: &DungeonParams, textures: Arc<Textures>) -> Dungeon {
+        let (_, dw, dh) = pick_size(params, rng);
+        let caves = &params.caves;
+        let theme = &MINES;
+
+        let mut grid = Grid::with_textures(dw, dh, textures.clone());
+        grid.fill(Tile::Wall(texture(&textures, theme.walls[0])));
+        let floor = theme.corridor(&textures);
+
+        let index = |x: u32, y: u32| (x + dw * y) as usize;
+
+        // The outer edge is always rock so the caves are closed in
+        let mut rock = vec![true; (dw * dh) as usize];
+        for x in 1..dw - 1 {
+            for y in 1..dh - 1 {
+                rock[index(x, y)] = rng.gen::<f64>() < caves.fill;
+            }
+        }
+
+        for _ in 0..caves.iterations {
+            let mut next = rock.clone();
+            for x in 1..dw - 1 {
+                for y in 1..dh - 1 {
+                    let mut count = 0;
+                    for nx in x - 1..x + 2 {
+                        for ny in y - 1..y + 2 {
+                            if rock[index(nx, ny)] {
+                                count += 1;
+                            }
+                        }
+                    }
+
+                    next[index(x, y)] = count >= 5;
+                }
+            }
+            rock = next;
+        }
+
+        for x in 0..dw {
+            for y in 0..dh {
+                if !rock[index(x, y)] {
+                    grid[(x, y)] = Tile::Floor(floor);
+                }
+            }
+        }
+
+        // Regions come largest first, so the biggest cavern always stays
+        let regions = grid.regions();
+        for region in regions.iter().skip(1).filter(|region| region.len() < caves.min_cavern) {
+            for &(x, y) in region {
+                grid[(x, y)] = Tile::Wall(texture(&textures, theme.walls[0]));
+            }
+        }
+
+        if regions.is_empty() {
+            grid[(dw / 2, dh / 2)] = Tile::Floor(floor);
+        }
+
+        connect(&mut grid, floor);
+        debug_assert!(grid.is_connected());
+
+        let (sx, sy) = *rng.choose(&grid.regions()[0]).unwrap();
+        let (lights, sprites) = furnish_open(&grid, (sx as f64 + 0.5, sy as f64 + 0.5), rng);
+        let light_map = LightMap::new(&grid, &lights);
+
+        Dungeon {
+            grid: grid,
+            lights: lights,
+            sprites: sprites,
+            room_graph: RoomGraph {
+                rooms: Vec::new(),
+                edges: Vec::new(),
+            },
+            light_map: light_map,
+            player_spawn: (sx as f64 + 0.5, sy as f64 + 0.5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caves(caves: CaveParams, seed: &str) -> Dungeon {
+        let params = DungeonParams { caves: caves, ..DungeonParams::default() };
+        Dungeon::with_generator(seed, Textures::builtin(), &Caves, &params)
+    }
+
+    #[test]
+    fn smoothing_opens_up_caverns() {
+        let rough = CaveParams { iterations: 0, min_cavern: 0, ..CaveParams::default() };
+        let smooth = CaveParams::default();
+
+        // Before smoothing the noise breaks up into lots of tiny pockets that
+        // all need tunnels; afterwards there are far fewer walls in the way
+        let walls = |dungeon: &Dungeon| {
+            let grid = &dungeon.grid;
+            (0..grid.width())
+                .flat_map(|x| (0..grid.height()).map(move |y| (x, y)))
+                .filter(|&(x, y)| grid[(x, y)].is_solid())
+                .count()
+        };
+
+        let rough = caves(rough, "mines");
+        let smooth = caves(smooth, "mines");
+        assert!(rough.grid.is_connected());
+        assert!(smooth.grid.is_connected());
+        assert!(walls(&smooth) < walls(&rough));
+    }
+
+    #[test]
+    fn solid_rock_still_has_somewhere_to_stand() {
+        let solid = CaveParams { fill: 1.0, ..CaveParams::default() };
+        let dungeon = caves(solid, "mines");
+
+        let (x, y) = dungeon.player_spawn();
+        assert!(!dungeon.grid[(x as u32, y as u32)].is_solid());
+    }
+}
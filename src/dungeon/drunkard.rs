@@ -0,0 +1,66 @@
+use rand::Rng;
+use rand::isaac::IsaacRng;
+
+use std::sync::Arc;
+
+use grid::{Grid, Tile};
+use light::LightMap;
+use texture::Textures;
+
+use super::*;
+
+// Share of the map the walk carves out before it stops
+const FLOOR_FRACTION: f64 = 0.35;
+
+/// Drunkard's walk: a digger wanders at random from the middle of the map,
+/// carving out floor as it goes. Gives winding, cave-like tunnels that are
+/// connected by construction.
+pub struct Drunkard;
+
+impl Generator for Drunkard {
+    fn generate(&self, rng: &mut IsaacRng, params: &DungeonParams, textures: Arc<Textures>) -> Dungeon {
+        let (_, dw, dh) = pick_size(params, rng);
+        let theme = rng.choose(THEMES).unwrap();
+
+        let mut grid = Grid::with_textures(dw, dh, textures.clone());
+        grid.fill(Tile::Wall(texture(&textures, rng.choose(theme.walls).unwrap())));
+
+        let floor = theme.corridor(&textures);
+        let target = ((dw - 2) * (dh - 2)) as f64 * FLOOR_FRACTION;
+
+        let (start_x, start_y) = (dw / 2, dh / 2);
+        let (mut x, mut y) = (start_x, start_y);
+        let mut carved = 0;
+        while (carved as f64) < target {
+            if let Tile::Wall(_) = grid[(x, y)] {
+                grid[(x, y)] = Tile::Floor(floor);
+                carved += 1;
+            }
+
+            // Stay off the outer edge so the map is always walled in
+            match rng.gen_range(0, 4) {
+                0 if x > 1 => x -= 1,
+                1 if x < dw - 2 => x += 1,
+                2 if y > 1 => y -= 1,
+                3 if y < dh - 2 => y += 1,
+                _ => {},
+            }
+        }
+
+        let spawn = (start_x as f64 + 0.5, start_y as f64 + 0.5);
+        let (lights, sprites) = furnish_open(&grid, spawn, rng);
+        let light_map = LightMap::new(&grid, &lights);
+
+        Dungeon {
+            grid: grid,
+            lights: lights,
+            sprites: sprites,
+            room_graph: RoomGraph {
+                rooms: Vec::new(),
+                edges: Vec::new(),
+            },
+            light_map: light_map,
+            player_spawn: spawn,
+        }
+    }
+}
@@ -0,0 +1,854 @@
+use rand::{Rng, SeedableRng};
+use rand::isaac::IsaacRng;
+use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
+use rand::distributions::range::Range;
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::io;
+use std::sync::Arc;
+
+use grid::Grid;
+use grid::{Material, Tile};
+use grid::{Door, DoorState};
+use light::{Light, LightMap};
+use sprite::Sprite;
+use texture::Textures;
+
+mod bsp;
+mod caves;
+mod drunkard;
+mod params;
+mod rooms;
+
+pub use self::bsp::Bsp;
+pub use self::caves::Caves;
+pub use self::drunkard::Drunkard;
+pub use self::params::{CaveParams, DungeonParams, RoomSizes, SizeParams};
+pub use self::rooms::Rooms;
+
+#[derive(Debug)]
+pub struct Dungeon {
+    pub grid: Grid,
+    pub lights: Vec<Light>,
+    pub sprites: Vec<Sprite>,
+    room_graph: RoomGraph,
+    light_map: LightMap, // Lighting from `lights` alone, see `relight`
+    player_spawn: (f64, f64),
+}
+
+/// Which rooms are joined by corridors. The corridors follow a minimum
+/// spanning tree of the room centres, plus a few extra edges between nearby
+/// rooms so the layout has loops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomGraph {
+    rooms: Vec<Room>,
+    edges: Vec<(usize, usize)>, // Indices into `rooms`, lowest first
+}
+
+// How many of a room's nearest neighbours can get a loop corridor
+const LOOP_NEIGHBOURS: usize = 3;
+
+/// A level layout algorithm. The same RNG state always gives the same
+/// dungeon.
+pub trait Generator {
+    fn generate(&self, rng: &mut IsaacRng, params: &DungeonParams, textures: Arc<Textures>) -> Dungeon;
+}
+
+/// Names accepted by `generator`, the default first.
+pub const GENERATORS: &[&str] = &["rooms", "bsp", "drunkard", "caves"];
+
+pub fn generator(name: &str) -> Option<Box<dyn Generator>> {
+    match name {
+        "rooms" => Some(Box::new(Rooms)),
+        "bsp" => Some(Box::new(Bsp)),
+        "drunkard" => Some(Box::new(Drunkard)),
+        "caves" => Some(Box::new(Caves)),
+        _ => None,
+    }
+}
+
+/// The textures a dungeon is built from, by name.
+struct Theme {
+    walls: &'static [&'static str], // Each room picks one of these
+    floor: &'static str,
+    rubble: &'static str,
+    corridor: &'static str,
+    ceiling: &'static str,
+}
+
+const THEMES: &[Theme] = &[
+    Theme {
+        walls: &["wall_rock", "wall_rock", "wall_mossy"],
+        floor: "floor_stone",
+        rubble: "floor_dirt",
+        corridor: "floor_dirt",
+        ceiling: "ceiling_rock",
+    },
+    Theme {
+        walls: &["wall_brick", "wall_brick", "wall_mossy"],
+        floor: "floor_stone",
+        rubble: "floor_dirt",
+        corridor: "floor_stone",
+        ceiling: "ceiling_rock",
+    },
+];
+
+// The Mines are dug straight out of the rock.
+const MINES: Theme = Theme {
+    walls: &["wall_rock", "wall_rock", "wall_mossy"],
+    floor: "floor_dirt",
+    rubble: "floor_dirt",
+    corridor: "floor_dirt",
+    ceiling: "ceiling_rock",
+};
+
+impl Theme {
+    // Room floors are mostly stone with the odd patch of rubble.
+    fn room_materials(&self, textures: &Textures) -> Vec<Weighted<Material>> {
+        let ceiling = texture(textures, self.ceiling);
+        vec![
+            Weighted { weight: 400, item: Material::new(texture(textures, self.floor), ceiling, 0.35, 1.0) },
+            Weighted { weight: 100, item: Material::new(texture(textures, self.rubble), ceiling, 0.2, 1.6) },
+        ]
+    }
+
+    fn corridor(&self, textures: &Textures) -> Material {
+        Material::new(texture(textures, self.corridor), texture(textures, self.ceiling), 0.25, 1.0)
+    }
+}
+
+impl Dungeon {
+    pub fn new_from_seed(seed: &str) -> Self {
+        Dungeon::with_textures(seed, Textures::builtin())
+    }
+
+    pub fn with_textures(seed: &str, textures: Arc<Textures>) -> Self {
+        Dungeon::with_generator(seed, textures, &Rooms, &DungeonParams::default())
+    }
+
+    pub fn with_generator(
+        seed: &str,
+        textures: Arc<Textures>,
+        generator: &dyn Generator,
+        params: &DungeonParams,
+    ) -> Self {
+        let seed_bytes = seed.as_bytes().iter().map(|n| *n as u32).collect::<Vec<u32>>();
+        let mut rng = IsaacRng::from_seed(&seed_bytes);
+
+        generator.generate(&mut rng, params, textures)
+    }
+
+    /// Wraps a hand-built grid, with no lights, sprites or rooms.
+    pub fn from_grid(grid: Grid, player_spawn: (f64, f64)) -> Self {
+        let light_map = LightMap::new(&grid, &[]);
+
+        Dungeon {
+            grid: grid,
+            lights: Vec::new(),
+            sprites: Vec::new(),
+            room_graph: RoomGraph { rooms: Vec::new(), edges: Vec::new() },
+            light_map: light_map,
+            player_spawn: player_spawn,
+        }
+    }
+
+    pub fn render_grid<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.grid.render_image(path)
+    }
+
+    pub fn player_spawn(&self) -> (f64, f64) {
+        self.player_spawn
+    }
+
+    pub fn room_graph(&self) -> &RoomGraph {
+        &self.room_graph
+    }
+
+    /// How the dungeon's own lights fall, without the player's.
+    pub fn light_map(&self) -> &LightMap {
+        &self.light_map
+    }
+
+    /// Rebuilds the light map. Needed after changing `lights` or opening or
+    /// closing a door, since closed doors block light.
+    pub fn relight(&mut self) {
+        self.light_map = LightMap::new(&self.grid, &self.lights);
+    }
+}
+
+impl RoomGraph {
+    fn new<R: Rng>(rooms: Vec<Room>, loop_fraction: f64, rng: &mut R) -> Self {
+        let n = rooms.len();
+        let dist = |a: usize, b: usize| {
+            (rooms[a].center_x() - rooms[b].center_x()).powi(2) +
+                (rooms[a].center_y() - rooms[b].center_y()).powi(2)
+        };
+        let by_dist = |a: f64, b: f64| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+
+        // Prim's algorithm, growing the tree from the first room
+        let mut edges = Vec::new();
+        let mut in_tree = vec![false; n];
+        let mut nearest: Vec<(f64, usize)> = (0..n).map(|i| (dist(0, i), 0)).collect();
+        in_tree[0] = true;
+
+        for _ in 1..n {
+            let next = (0..n)
+                .filter(|&i| !in_tree[i])
+                .min_by(|&a, &b| by_dist(nearest[a].0, nearest[b].0))
+                .unwrap();
+
+            in_tree[next] = true;
+            let from = nearest[next].1;
+            edges.push((from.min(next), from.max(next)));
+
+            for i in 0..n {
+                if !in_tree[i] && dist(next, i) < nearest[i].0 {
+                    nearest[i] = (dist(next, i), next);
+                }
+            }
+        }
+
+        for a in 0..n {
+            let mut neighbours: Vec<usize> = (0..n).filter(|&b| b != a).collect();
+            neighbours.sort_by(|&b, &c| by_dist(dist(a, b), dist(a, c)));
+
+            for &b in neighbours.iter().take(LOOP_NEIGHBOURS) {
+                // Always roll, so the fraction doesn't change the rest of
+                // the layout
+                let roll = rng.gen::<f64>();
+                let edge = (a.min(b), a.max(b));
+                if roll < loop_fraction && !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        RoomGraph {
+            rooms: rooms,
+            edges: edges,
+        }
+    }
+
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Rooms with a corridor straight to `room`.
+    pub fn neighbours(&self, room: usize) -> Vec<usize> {
+        self.edges.iter()
+            .filter_map(|&(a, b)| {
+                if a == room {
+                    Some(b)
+                } else if b == room {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The room containing tile (`x`, `y`), if any.
+    pub fn room_at(&self, x: u32, y: u32) -> Option<usize> {
+        self.rooms.iter().position(|room| room.contains(x, y))
+    }
+}
+
+// Themes only name builtin textures, and every `Textures` has those.
+fn texture(textures: &Textures, name: &str) -> usize {
+    textures.id(name).expect("Missing builtin texture")
+}
+
+// Picks how big the dungeon is, then its width and height.
+fn pick_size<R: Rng>(params: &DungeonParams, rng: &mut R) -> (DungeonSize, u32, u32) {
+    let dungeon_sizes = &mut [
+        Weighted { weight: params.small.weight, item: DungeonSize::Small },
+        Weighted { weight: params.medium.weight, item: DungeonSize::Med },
+        Weighted { weight: params.large.weight, item: DungeonSize::Large },
+    ];
+    let dungeon_size = WeightedChoice::new(dungeon_sizes).ind_sample(rng);
+
+    let dungeon_bounds = dungeon_size.params(params).size;
+    let dw = pick(dungeon_bounds, rng);
+    let dh = pick(dungeon_bounds, rng);
+
+    (dungeon_size, dw, dh)
+}
+
+// A number from an inclusive [min, max] range.
+fn pick<R: Rng>((min, max): (u32, u32), rng: &mut R) -> u32 {
+    Range::new(min, max + 1).ind_sample(rng)
+}
+
+// Floors each room and walls it in with one of the theme's walls.
+fn build_rooms<R: Rng>(grid: &mut Grid, rooms: &[Room], theme: &Theme, rng: &mut R) {
+    let textures = grid.textures().clone();
+    let room_materials = &mut theme.room_materials(&textures);
+    let room_material = WeightedChoice::new(room_materials);
+
+    for room in rooms {
+        let material = room_material.ind_sample(rng);
+        let wall = texture(&textures, rng.choose(theme.walls).unwrap());
+
+        // The ring of wall around the room, clipped to the grid
+        let x1 = room.x1().saturating_sub(1);
+        let y1 = room.y1().saturating_sub(1);
+        let x2 = (room.x2() + 1).min(grid.width());
+        let y2 = (room.y2() + 1).min(grid.height());
+        for x in x1..x2 {
+            for y in y1..y2 {
+                grid[(x,y)] = Tile::Wall(wall);
+            }
+        }
+
+        for x in room.x1()..room.x2() {
+            for y in room.y1()..room.y2() {
+                grid[(x,y)] = Tile::Floor(material);
+            }
+        }
+    }
+}
+
+// Runs an L-shaped corridor `width` tiles wide between the centres of two
+// rooms.
+fn join<R: Rng>(grid: &mut Grid, material: Material, width: u32, a: &Room, b: &Room, rng: &mut R) {
+    let (ax, ay) = (a.center_x() as u32, a.center_y() as u32);
+    let (bx, by) = (b.center_x() as u32, b.center_y() as u32);
+
+    // Random coin flip?
+    match rng.gen::<bool>() {
+        true => {
+            carve_h(grid, material, width, ax, bx, ay);
+            carve_v(grid, material, width, ay, by, bx);
+        },
+        false => {
+            carve_v(grid, material, width, ay, by, ax);
+            carve_h(grid, material, width, ax, bx, by);
+        },
+    }
+}
+
+// Torches and sprites for a dungeon made of rooms.
+fn furnish<R: Rng>(
+    rooms: &[Room],
+    spawn: (f64, f64),
+    textures: &Textures,
+    rng: &mut R,
+) -> (Vec<Light>, Vec<Sprite>) {
+    // Roughly every other room gets a torch somewhere along its walls
+    let mut lights = Vec::new();
+    for room in rooms {
+        if rng.gen_weighted_bool(2) {
+            let (tx, ty) = match rng.gen_range(0, 4) {
+                0 => (rng.gen_range(room.x1(), room.x2()), room.y1()),
+                1 => (rng.gen_range(room.x1(), room.x2()), room.y2() - 1),
+                2 => (room.x1(), rng.gen_range(room.y1(), room.y2())),
+                _ => (room.x2() - 1, rng.gen_range(room.y1(), room.y2())),
+            };
+
+            lights.push(Light::torch(tx as f64 + 0.5, ty as f64 + 0.5));
+        }
+    }
+
+    let mut sprites = Vec::new();
+    for room in rooms {
+        if rng.gen_weighted_bool(3) {
+            let sx = rng.gen_range(room.x1(), room.x2()) as f64 + 0.5;
+            let sy = rng.gen_range(room.y1(), room.y2()) as f64 + 0.5;
+            sprites.push(Sprite::new(sx, sy, texture(textures, "sprite_chest")));
+        }
+
+        if rng.gen_weighted_bool(4) {
+            let sx = rng.gen_range(room.x1(), room.x2()) as f64 + 0.5;
+            let sy = rng.gen_range(room.y1(), room.y2()) as f64 + 0.5;
+            sprites.push(Sprite::new(sx, sy, texture(textures, "sprite_slime")));
+        }
+    }
+
+    // One peddler, in the middle of the room furthest from the spawn
+    let far = rooms.iter().max_by(|a, b| {
+        distance(a.center(), spawn).partial_cmp(&distance(b.center(), spawn)).unwrap_or(Ordering::Equal)
+    });
+    if let Some(room) = far {
+        let (sx, sy) = (room.center_x().floor() + 0.5, room.center_y().floor() + 0.5);
+        sprites.push(Sprite::new(sx, sy, texture(textures, "sprite_peddler")));
+    }
+
+    (lights, sprites)
+}
+
+// Torches and sprites scattered over the open floor of a dungeon without
+// rooms, about as densely as `furnish` places them.
+fn furnish_open<R: Rng>(grid: &Grid, spawn: (f64, f64), rng: &mut R) -> (Vec<Light>, Vec<Sprite>) {
+    let mut floor = Vec::new();
+    for x in 0..grid.width() {
+        for y in 0..grid.height() {
+            if let Tile::Floor(_) = grid[(x, y)] {
+                floor.push((x as f64 + 0.5, y as f64 + 0.5));
+            }
+        }
+    }
+
+    let textures = grid.textures();
+    let mut lights = Vec::new();
+    let mut sprites = Vec::new();
+    for _ in 0..floor.len() / 200 {
+        let &(x, y) = rng.choose(&floor).unwrap();
+        lights.push(Light::torch(x, y));
+    }
+    for _ in 0..floor.len() / 300 {
+        let &(x, y) = rng.choose(&floor).unwrap();
+        sprites.push(Sprite::new(x, y, texture(textures, "sprite_chest")));
+    }
+    for _ in 0..floor.len() / 400 {
+        let &(x, y) = rng.choose(&floor).unwrap();
+        sprites.push(Sprite::new(x, y, texture(textures, "sprite_slime")));
+    }
+
+    // The peddler waits on the floor furthest from the spawn
+    let far = floor.iter().max_by(|&&a, &&b| {
+        distance(a, spawn).partial_cmp(&distance(b, spawn)).unwrap_or(Ordering::Equal)
+    });
+    if let Some(&(x, y)) = far {
+        sprites.push(Sprite::new(x, y, texture(textures, "sprite_peddler")));
+    }
+
+    (lights, sprites)
+}
+
+fn distance((ax, ay): (f64, f64), (bx, by): (f64, f64)) -> f64 {
+    (ax - bx).hypot(ay - by)
+}
+
+fn carve_h(grid: &mut Grid, material: Material, width: u32, x1: u32, x2: u32, y: u32) {
+    let lowest = if x1 < x2 { x1 } else { x2 };
+    let highest = if x1 > x2 { x1 } else { x2 };
+
+    for x in lowest..highest + width {
+        for y in y..y + width {
+            carve(grid, material, x, y);
+        }
+    }
+}
+
+fn carve_v(grid: &mut Grid, material: Material, width: u32, y1: u32, y2: u32, x: u32) {
+    let lowest = if y1 < y2 { y1 } else { y2 };
+    let highest = if y1 > y2 { y1 } else { y2 };
+
+    for y in lowest..highest + width {
+        for x in x..x + width {
+            carve(grid, material, x, y);
+        }
+    }
+}
+
+// Joins any part of the dungeon that can't be reached from the rest to its
+// nearest neighbour with an L-shaped corridor.
+fn connect(grid: &mut Grid, material: Material) {
+    loop {
+        let regions = grid.regions();
+        if regions.len() <= 1 {
+            return;
+        }
+
+        // Search outwards from the smallest region, through rock, until
+        // some other walkable tile turns up. Every tile remembers which
+        // tile of the region the search reached it from.
+        let width = grid.width();
+        let index = |x: u32, y: u32| (x + width * y) as usize;
+
+        let mut origin = vec![None; (width * grid.height()) as usize];
+        let mut queue = VecDeque::new();
+        for &(x, y) in regions.last().unwrap() {
+            origin[index(x, y)] = Some((x, y));
+            queue.push_back((x, y));
+        }
+
+        let mut nearest = None;
+        'search: while let Some((x, y)) = queue.pop_front() {
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+
+            for &(nx, ny) in &neighbours {
+                let walkable = match grid.get(nx, ny) {
+                    Some(tile) => tile.material().is_some(),
+                    None => continue,
+                };
+
+                let n = index(nx, ny);
+                if origin[n].is_some() {
+                    continue;
+                }
+                origin[n] = origin[index(x, y)];
+
+                // Regions are as big as they can be, so this is another one
+                if walkable {
+                    nearest = Some((origin[n].unwrap(), (nx, ny)));
+                    break 'search;
+                }
+                queue.push_back((nx, ny));
+            }
+        }
+
+        let ((fx, fy), (tx, ty)) = nearest.unwrap();
+        carve_h(grid, material, 1, fx, tx, fy);
+        carve_v(grid, material, 1, fy, ty, tx);
+    }
+}
+
+// Puts a door wherever a corridor crosses the ring of wall around a room.
+// Nothing can unlock a door yet, so they're never generated locked.
+fn place_doors<R: Rng>(grid: &mut Grid, rooms: &[Room], texture: usize, rng: &mut R) {
+    for room in rooms {
+        let mut candidates = Vec::new();
+
+        for x in room.x1()..room.x2() {
+            if room.y1() > 0 {
+                candidates.push((x, room.y1() - 1, true));
+            }
+            candidates.push((x, room.y2(), true));
+        }
+
+        for y in room.y1()..room.y2() {
+            if room.x1() > 0 {
+                candidates.push((room.x1() - 1, y, false));
+            }
+            candidates.push((room.x2(), y, false));
+        }
+
+        for (x, y, horizontal) in candidates {
+            let material = match grid.get(x, y) {
+                Some(&Tile::Floor(material)) => material,
+                _ => continue,
+            };
+
+            // Both sides of the doorway have to be wall, or this is a
+            // corridor running alongside the room rather than into it
+            let (side_a, side_b) = if horizontal {
+                (x.checked_sub(1).and_then(|sx| grid.get(sx, y)), grid.get(x + 1, y))
+            } else {
+                (y.checked_sub(1).and_then(|sy| grid.get(x, sy)), grid.get(x, y + 1))
+            };
+
+            match (side_a, side_b) {
+                (Some(&Tile::Wall(_)), Some(&Tile::Wall(_))) => {},
+                _ => continue,
+            }
+
+            let state = if rng.gen_weighted_bool(4) {
+                DoorState::Open
+            } else {
+                DoorState::Closed
+            };
+
+            grid[(x, y)] = Tile::Door(Door::new(state, horizontal, texture, material));
+        }
+    }
+}
+
+// Corridors get their own floor, but leave the rooms they pass through alone.
+// Wide corridors can run off the edge of the map, which is left as it is.
+fn carve(grid: &mut Grid, material: Material, x: u32, y: u32) {
+    if let Some(&Tile::Wall(_)) = grid.get(x, y) {
+        grid[(x, y)] = Tile::Floor(material);
+    }
+}
+
+#[derive(Clone)]
+enum DungeonSize {
+    Small,
+    Med,
+    Large,
+}
+
+#[derive(Clone)]
+enum RoomSize {
+    Small,
+    Med,
+    Large,
+}
+
+impl DungeonSize {
+    fn params<'a>(&self, params: &'a DungeonParams) -> &'a SizeParams {
+        match *self {
+            DungeonSize::Small => &params.small,
+            DungeonSize::Med => &params.medium,
+            DungeonSize::Large => &params.large,
+        }
+    }
+}
+
+impl RoomSize {
+    fn bounds(&self, sizes: &RoomSizes) -> (u32, u32) {
+        match *self {
+            RoomSize::Small => sizes.small,
+            RoomSize::Med => sizes.medium,
+            RoomSize::Large => sizes.large,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Room {
+    x: u32, // X coordinate of top-left
+    y: u32, // Y coordinate of top-right
+    w: u32,
+    h: u32,
+}
+
+impl Room {
+    fn new(x: u32, y: u32, w: u32, h: u32) -> Self {
+        Room {x: x, y: y, w: w, h: h}
+    }
+
+    pub fn x1(&self) -> u32 {
+        self.x
+    }
+
+    pub fn x2(&self) -> u32 {
+        self.x + self.w
+    }
+
+    pub fn y1(&self) -> u32 {
+        self.y
+    }
+
+    pub fn y2(&self) -> u32 {
+        self.y + self.h
+    }
+
+    pub fn center_x(&self) -> f64 {
+        self.x as f64 + (self.w as f64 / 2.0)
+    }
+
+    pub fn center_y(&self) -> f64 {
+        self.y as f64 + (self.h as f64 / 2.0)
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.center_x(), self.center_y())
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x1() && x < self.x2() && y >= self.y1() && y < self.y2()
+    }
+
+    fn intersects(&self, other: &Room) -> bool {
+        self.x1() <= other.x2() && self.x2() >= other.x1() &&
+            self.y1() <= other.y2() && self.y2() >= other.y1()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generates `seeds` dungeons with each generator and checks they can be
+    // walked end to end from the spawn.
+    fn check_connected(seeds: u32) {
+        let params = DungeonParams::default();
+        for name in GENERATORS {
+            let generator = generator(name).unwrap();
+
+            for n in 0..seeds {
+                let seed = n.to_string();
+                let dungeon = Dungeon::with_generator(&seed, Textures::builtin(), &*generator, &params);
+                assert!(dungeon.grid.is_connected(), "{} seed {} is not connected", name, seed);
+
+                let (px, py) = dungeon.player_spawn();
+                let spawn = dungeon.grid.get(px as u32, py as u32).unwrap();
+                assert!(spawn.material().is_some(), "{} seed {} spawns in a wall", name, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn dungeons_are_connected() {
+        check_connected(40);
+    }
+
+    // Slow, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn thousands_of_dungeons_are_connected() {
+        check_connected(3000);
+    }
+
+    #[test]
+    fn params_shape_the_dungeon() {
+        let mut params = DungeonParams::default();
+        params.small.weight = 0;
+        params.medium.weight = 0;
+        params.corridor_width = 2;
+
+        for n in 0..50 {
+            let dungeon = Dungeon::with_generator(&n.to_string(), Textures::builtin(), &Rooms, &params);
+            assert!(dungeon.grid.width() >= 120 && dungeon.grid.height() >= 120);
+            assert!(dungeon.grid.is_connected());
+        }
+    }
+
+    #[test]
+    fn wide_corridors_are_carved() {
+        let material = Material::new(0, 0, 0.25, 1.0);
+        let mut grid = Grid::new(12, 12);
+
+        // An L from (8, 3) back to (2, 3) and down to (2, 9), two tiles wide
+        carve_h(&mut grid, material, 2, 8, 2, 3);
+        carve_v(&mut grid, material, 2, 3, 9, 2);
+
+        for x in 0..12 {
+            for y in 0..12 {
+                let across = x >= 2 && x < 10 && y >= 3 && y < 5;
+                let down = x >= 2 && x < 4 && y >= 3 && y < 11;
+                assert_eq!(grid[(x, y)].is_solid(), !(across || down), "tile {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn generators_are_deterministic() {
+        for name in GENERATORS {
+            let generator = generator(name).unwrap();
+            let params = DungeonParams::default();
+            let a = Dungeon::with_generator("same", Textures::builtin(), &*generator, &params);
+            let b = Dungeon::with_generator("same", Textures::builtin(), &*generator, &params);
+            assert_eq!(a.grid.regions(), b.grid.regions());
+            assert_eq!(a.player_spawn(), b.player_spawn());
+        }
+
+        assert!(generator("nope").is_none());
+    }
+
+    #[test]
+    fn every_dungeon_has_a_peddler() {
+        let params = DungeonParams::default();
+        let peddler = Textures::builtin().id("sprite_peddler").unwrap();
+        for name in GENERATORS {
+            let generator = generator(name).unwrap();
+            for n in 0..10 {
+                let dungeon = Dungeon::with_generator(&n.to_string(), Textures::builtin(), &*generator, &params);
+                let peddlers: Vec<_> = dungeon.sprites.iter().filter(|s| s.texture == peddler).collect();
+                assert_eq!(peddlers.len(), 1);
+
+                let (x, y) = (peddlers[0].x, peddlers[0].y);
+                assert!(!dungeon.grid[(x as u32, y as u32)].is_solid());
+                assert!(distance((x, y), dungeon.player_spawn()) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_room_graphs_are_connected() {
+        let params = DungeonParams::default();
+        for generator in &[&Rooms as &dyn Generator, &Bsp] {
+            for n in 0..20 {
+                let dungeon = Dungeon::with_generator(&n.to_string(), Textures::builtin(), *generator, &params);
+                let graph = dungeon.room_graph();
+                let count = graph.rooms().len();
+                assert!(count > 0);
+
+                for &(a, b) in graph.edges() {
+                    assert!(a < b && b < count);
+                }
+
+                // Every room can be reached from the first along corridors
+                let mut seen = vec![false; count];
+                let mut queue = VecDeque::new();
+                seen[0] = true;
+                queue.push_back(0);
+                while let Some(room) = queue.pop_front() {
+                    for next in graph.neighbours(room) {
+                        if !seen[next] {
+                            seen[next] = true;
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                assert!(seen.iter().all(|&seen| seen), "seed {} has unreachable rooms", n);
+            }
+        }
+    }
+
+    #[test]
+    fn room_graph_spans_every_room() {
+        let rooms: Vec<Room> = (0..12)
+            .map(|i| Room::new((i % 4) * 12, (i / 4) * 12, 8, 8))
+            .collect();
+
+        let tree = RoomGraph::new(rooms.clone(), 0.0, &mut IsaacRng::new_unseeded());
+        assert_eq!(tree.edges().len(), rooms.len() - 1);
+        // Neighbouring rooms in the lattice are the nearest, so the tree
+        // never jumps across the map
+        for &(a, b) in tree.edges() {
+            assert!(b - a == 1 || b - a == 4);
+        }
+
+        let looped = RoomGraph::new(rooms.clone(), 1.0, &mut IsaacRng::new_unseeded());
+        assert!(looped.edges().len() > tree.edges().len());
+        assert!(looped.neighbours(5).contains(&4));
+        assert!(looped.neighbours(4).contains(&5));
+
+        assert_eq!(tree.room_at(13, 14), Some(5));
+        assert_eq!(tree.room_at(10, 10), None);
+    }
+
+    #[test]
+    fn connect_joins_regions() {
+        let material = Material::new(0, 0, 0.25, 1.0);
+        let mut grid = Grid::from_rows(&[
+            "############",
+            "#...##.....#",
+            "#..###.....#",
+            "############",
+            "############",
+            "#.##########",
+            "############",
+        ], Material::new(0, 0, 0.35, 1.0));
+        assert_eq!(grid.regions().len(), 3);
+
+        // The lone tile is joined straight up to the nearest room, then that
+        // room through the thin wall to the other one
+        connect(&mut grid, material);
+        assert!(grid.is_connected());
+
+        let tunnel = [(1, 3), (1, 4), (4, 1), (5, 1)];
+        for &(x, y) in &tunnel {
+            assert_eq!(grid[(x, y)], Tile::Floor(material));
+        }
+        let floor = (0..grid.width())
+            .flat_map(|x| (0..grid.height()).map(move |y| (x, y)))
+            .filter(|&(x, y)| !grid[(x, y)].is_solid())
+            .count();
+        assert_eq!(floor, 5 + 10 + 1 + tunnel.len());
+    }
+
+    #[test]
+    fn relight_follows_doors() {
+        let floor = Material::new(0, 0, 0.0, 1.0);
+        let grid = Grid::from_rows(&[
+            "#######",
+            "#.....#",
+            "#######",
+        ], floor);
+        let mut dungeon = Dungeon::from_grid(grid, (1.5, 1.5));
+        dungeon.lights.push(Light::torch(1.5, 1.5));
+        dungeon.relight();
+        assert!(dungeon.light_map().get(5, 1).unwrap() > 0.0);
+
+        dungeon.grid[(3, 1)] = Tile::Door(Door::new(DoorState::Closed, false, 0, floor));
+        dungeon.relight();
+        assert_eq!(dungeon.light_map().get(5, 1), Some(0.0));
+        assert!(dungeon.light_map().get(2, 1).unwrap() > 0.0);
+    }
+}
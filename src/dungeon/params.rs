@@ -0,0 +1,266 @@
+use toml;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// The knobs for dungeon generation, loadable from a TOML file so levels
+/// can be tuned without recompiling. Ranges are `[min, max]`, inclusive.
+///
+/// ```toml
+/// attempts = [20, 40]
+/// corridor_width = 1
+/// loop_fraction = 0.15
+///
+/// [small]
+/// weight = 300
+/// size = [50, 80]
+/// max_rooms = [7, 10]
+/// room_weights = [100, 200, 0]
+///
+/// [room_sizes]
+/// small = [5, 10]
+///
+/// [caves]
+/// fill = 0.45
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DungeonParams {
+    pub attempts: (u32, u32), // Tries at placing a room
+    pub corridor_width: u32,
+    pub loop_fraction: f64, // Chance of a loop corridor to each nearby room
+    pub small: SizeParams,
+    pub medium: SizeParams,
+    pub large: SizeParams,
+    pub room_sizes: RoomSizes,
+    pub caves: CaveParams,
+}
+
+/// One size of dungeon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeParams {
+    pub weight: u32, // How often this size comes up, relative to the others
+    pub size: (u32, u32), // Width and height are each picked from this
+    pub max_rooms: (usize, usize),
+    pub room_weights: (u32, u32, u32), // Small, medium and large rooms
+}
+
+/// Width and height ranges for each size of room.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomSizes {
+    pub small: (u32, u32),
+    pub medium: (u32, u32),
+    pub large: (u32, u32),
+}
+
+/// Tuning for the cellular automaton behind `Caves`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveParams {
+    pub fill: f64, // Chance of each tile starting as rock
+    pub iterations: u32, // Smoothing passes
+    pub min_cavern: usize, // Caverns with fewer tiles than this are filled in
+}
+
+impl DungeonParams {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<DungeonParams> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        DungeonParams::parse(&text)
+    }
+
+    /// Loads `path`, or the defaults if there's no such file. Any other
+    /// error, like a bad value in the file, is returned.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> io::Result<DungeonParams> {
+        match DungeonParams::load(path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(DungeonParams::default()),
+            result => result,
+        }
+    }
+
+    /// Parses and checks a params file.
+    pub fn parse(text: &str) -> io::Result<DungeonParams> {
+        let params: DungeonParams = toml::from_str(text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        params.check().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(params)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        File::create(path)?.write_all(text.as_bytes())
+    }
+
+    /// Whether the generators can work with these values.
+    pub fn check(&self) -> Result<(), String> {
+        fn range<T: PartialOrd + fmt::Display>(name: &str, (min, max): (T, T)) -> Result<(), String> {
+            if min > max {
+                Err(format!("{} goes from {} down to {}", name, min, max))
+            } else {
+                Ok(())
+            }
+        }
+
+        range("attempts", self.attempts)?;
+        if self.attempts.0 == 0 {
+            return Err("attempts must be at least 1".to_string());
+        }
+
+        if self.corridor_width == 0 {
+            return Err("corridor_width must be at least 1".to_string());
+        }
+
+        if !(self.loop_fraction >= 0.0 && self.loop_fraction <= 1.0) {
+            return Err("loop_fraction must be between 0 and 1".to_string());
+        }
+
+        if self.small.weight + self.medium.weight + self.large.weight == 0 {
+            return Err("at least one dungeon size needs a weight".to_string());
+        }
+
+        let largest_room = self.room_sizes.small.1
+            .max(self.room_sizes.medium.1)
+            .max(self.room_sizes.large.1);
+
+        for &(name, size) in &[("small", &self.small), ("medium", &self.medium), ("large", &self.large)] {
+            range(&format!("{}.size", name), size.size)?;
+            range(&format!("{}.max_rooms", name), size.max_rooms)?;
+
+            if size.weight == 0 {
+                continue;
+            }
+
+            if size.size.0 < 3 {
+                return Err(format!("{} dungeons need to be at least 3 tiles across", name));
+            }
+
+            // Rooms are placed anywhere that leaves them inside the map
+            if size.size.0 <= largest_room {
+                return Err(format!("{} dungeons must be wider than the largest room", name));
+            }
+
+            let (small, medium, large) = size.room_weights;
+            if small + medium + large == 0 {
+                return Err(format!("{}.room_weights are all 0", name));
+            }
+        }
+
+        range("room_sizes.small", self.room_sizes.small)?;
+        range("room_sizes.medium", self.room_sizes.medium)?;
+        range("room_sizes.large", self.room_sizes.large)?;
+        for &(name, (min, _)) in &[
+            ("small", self.room_sizes.small),
+            ("medium", self.room_sizes.medium),
+            ("large", self.room_sizes.large),
+        ] {
+            if min == 0 {
+                return Err(format!("{} rooms must be at least 1 tile", name));
+            }
+        }
+
+        if !(self.caves.fill >= 0.0 && self.caves.fill <= 1.0) {
+            return Err("caves.fill must be between 0 and 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DungeonParams {
+    fn default() -> Self {
+        DungeonParams {
+            attempts: (20, 40),
+            corridor_width: 1,
+            loop_fraction: 0.15,
+            small: SizeParams {
+                weight: 300,
+                size: (50, 80),
+                max_rooms: (7, 10),
+                room_weights: (100, 200, 0),
+            },
+            medium: SizeParams {
+                weight: 250,
+                size: (80, 120),
+                max_rooms: (10, 20),
+                room_weights: (100, 250, 50),
+            },
+            large: SizeParams {
+                weight: 100,
+                size: (120, 150),
+                max_rooms: (20, 39),
+                room_weights: (100, 250, 100),
+            },
+            room_sizes: RoomSizes::default(),
+            caves: CaveParams::default(),
+        }
+    }
+}
+
+impl Default for RoomSizes {
+    fn default() -> Self {
+        RoomSizes {
+            small: (5, 10),
+            medium: (10, 14),
+            large: (15, 20),
+        }
+    }
+}
+
+impl Default for CaveParams {
+    fn default() -> Self {
+        CaveParams {
+            fill: 0.45,
+            iterations: 5,
+            min_cavern: 40,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_file::TempFile;
+
+    #[test]
+    fn shipped_params_are_the_defaults() {
+        let params = DungeonParams::parse(include_str!("../../resources/dungeon.toml")).unwrap();
+        assert_eq!(params, DungeonParams::default());
+        assert_eq!(DungeonParams::parse("").unwrap(), DungeonParams::default());
+    }
+
+    #[test]
+    fn only_missing_files_fall_back_to_the_defaults() {
+        let missing = TempFile::new("missing_params.toml");
+        assert_eq!(DungeonParams::load_or_default(missing.path()).unwrap(), DungeonParams::default());
+
+        let bad = TempFile::new("bad_params.toml");
+        File::create(bad.path()).unwrap().write_all(b"corridor_width = 0\n").unwrap();
+        assert!(DungeonParams::load_or_default(bad.path()).is_err());
+    }
+
+    #[test]
+    fn bad_params_are_rejected() {
+        let params = DungeonParams::parse("corridor_width = 2\n[room_sizes]\nlarge = [15, 30]\n").unwrap();
+        assert_eq!(params.corridor_width, 2);
+        assert_eq!(params.room_sizes.large, (15, 30));
+        assert_eq!(params.room_sizes.small, (5, 10));
+
+        assert!(DungeonParams::parse("attempts = [40, 20]\n").is_err());
+        assert!(DungeonParams::parse("attempts = [0, 0]\n").is_err());
+        assert!(DungeonParams::parse("attempts = [0, 10]\n").is_err());
+        assert!(DungeonParams::parse("corridor_width = 0\n").is_err());
+        assert!(DungeonParams::parse("loop_fraction = 1.5\n").is_err());
+        assert!(DungeonParams::parse("[caves]\nfill = -0.1\n").is_err());
+        assert!(DungeonParams::parse("[room_sizes]\nlarge = [15, 60]\n").is_err());
+        assert!(DungeonParams::parse("[room_sizes]\nsmall = [0, 10]\n").is_err());
+        assert!(DungeonParams::parse("[room_sizes]\nmedium = [0, 14]\n").is_err());
+        assert!(DungeonParams::parse("[room_sizes]\nlarge = [0, 20]\n").is_err());
+    }
+}
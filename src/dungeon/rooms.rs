@@ -0,0 +1,95 @@
+use rand::Rng;
+use rand::isaac::IsaacRng;
+use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
+
+use std::sync::Arc;
+
+use grid::{Grid, Tile};
+use light::LightMap;
+use texture::Textures;
+
+use super::*;
+
+/// Rooms scattered at random, joined by corridors along a spanning tree of
+/// the rooms with a few loops. The original generator.
+pub struct Rooms;
+
+impl Generator for Rooms {
+    fn generate(&self, rng: &mut IsaacRng, params: &DungeonParams, textures: Arc<Textures>) -> Dungeon {
+        let (dungeon_size, dw, dh) = pick_size(params, rng);
+        let size = dungeon_size.params(params);
+
+        let theme = rng.choose(THEMES).unwrap();
+        let texture = |name: &str| super::texture(&textures, name);
+
+        let mut grid = Grid::with_textures(dw, dh, textures.clone());
+        grid.fill(Tile::Wall(texture(theme.walls[0])));
+
+        let attempts = pick(params.attempts, rng);
+        let max_rooms = rng.gen_range(size.max_rooms.0, size.max_rooms.1 + 1);
+
+        let mut rooms: Vec<Room> = Vec::new();
+
+        'create_rooms: for _ in 0..attempts {
+            let (small_weight, med_weight, large_weight) = size.room_weights;
+            let room_sizes = &mut [
+                Weighted { weight: small_weight, item: RoomSize::Small },
+                Weighted { weight: med_weight, item: RoomSize::Med },
+                Weighted { weight: large_weight, item: RoomSize::Large },
+            ];
+
+            let room_size = WeightedChoice::new(room_sizes).ind_sample(rng);
+            let room_bounds = room_size.bounds(&params.room_sizes);
+
+            let rw = pick(room_bounds, rng);
+            let rh = pick(room_bounds, rng);
+
+            let rx = rng.gen_range(0, dw - rw);
+            let ry = rng.gen_range(0, dh - rh);
+
+            let room = Room::new(
+                rx,
+                ry,
+                rw,
+                rh,
+            );
+
+            for r in &rooms {
+                if r.intersects(&room) {
+                    continue 'create_rooms;
+                }
+            }
+
+            rooms.push(room);
+            if rooms.len() == max_rooms {
+                break 'create_rooms;
+            }
+        }
+
+        build_rooms(&mut grid, &rooms, theme, rng);
+        let corridor = theme.corridor(&textures);
+
+        let graph = RoomGraph::new(rooms.clone(), params.loop_fraction, rng);
+        for &(a, b) in graph.edges() {
+            join(&mut grid, corridor, params.corridor_width, &rooms[a], &rooms[b], rng);
+        }
+
+        connect(&mut grid, corridor);
+        debug_assert!(grid.is_connected());
+        place_doors(&mut grid, &rooms, texture("door_wood"), rng);
+
+        let (px, py) = rng.choose(&rooms).unwrap().center();
+
+        let (lights, sprites) = furnish(&rooms, (px, py), &textures, rng);
+        let light_map = LightMap::new(&grid, &lights);
+
+        Dungeon {
+            grid: grid,
+            lights: lights,
+            sprites: sprites,
+            room_graph: graph,
+            light_map: light_map,
+            player_spawn: (px, py),
+        }
+    }
+}
@@ -1,41 +1,74 @@
 use ggez::{Context, timer};
-use ggez::graphics::{self, Point, Rect, Color, DrawMode, GraphicsContext};
-use ggez::event::{EventHandler, Keycode, Mod, MouseState};
+use ggez::graphics::{self, DrawMode, DrawParam, FilterMode, Point};
+use ggez::event::{Axis, Button, EventHandler, Keycode, Mod, MouseButton, MouseState};
 use ggez::error::GameResult;
-
-use picto::pixel::Read;
-use picto::Region;
-use picto::processing::prelude::*;
-use picto::processing::scaler::Nearest;
 use picto::buffer::Rgba as RgbaImage;
-use picto::color::Rgba;
-use picto::write;
 
-use std::f64;
+use std::f64::consts::PI;
+use std::mem;
 use std::time::Duration;
 
-use dungeon::Dungeon;
+use collision;
+use dungeon::{Dungeon, DungeonParams};
 use grid::Tile;
-use player::Player;
-
-const TAU: f64 = 2.0 * f64::consts::PI;
-const FPS: u32 = 24;
+use input::{Action, Bindings, Gamepad, Input};
+use player::{Player, wrap_angle};
+use renderer::Renderer;
+use replay::{Event, Recording};
+use settings::Settings;
+
+/// Simulation ticks per second, regardless of how fast frames are drawn.
+pub const TICK_RATE: u32 = 60;
+pub const TICK: f64 = 1.0 / TICK_RATE as f64;
+
+// Longest frame the simulation will try to catch up on
+const MAX_FRAME_TIME: f64 = 0.25;
+
+// Seconds to animate a turn-based step across a tile of walk cost 1.0, and a
+// quarter turn
+const STEP_TIME: f64 = 0.18;
+const TURN_TIME: f64 = 0.12;
+
+/// How the player moves through the dungeon.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    RealTime,
+    TurnBased, // Every action moves one tile or turns 90 degrees
+}
 
-lazy_static! {
-    static ref BLACK: Rgba = Rgba::new(0.0, 0.0, 0.0, 1.0);
-    static ref WHITE: Rgba = Rgba::new(1.0, 1.0, 1.0, 1.0);
-    static ref RED: Rgba = Rgba::new(0.2, 0.0, 0.0, 1.0);
-    static ref LIGHT_GRAY: Rgba = Rgba::new(0.7, 0.7, 0.7, 1.0);
-    static ref DARK_GRAY: Rgba = Rgba::new(0.4, 0.4, 0.4, 1.0);
+// A turn-based move on its way from one pose to the next. The player is
+// already at `to`; this only animates the view.
+struct Step {
+    from: (f64, f64, f64), // x, y, angle
+    to: (f64, f64, f64),
+    duration: f64,
+    progress: f64, // 0.0 to 1.0
 }
 
 pub struct Game {
     dungeon: Dungeon,
     player: Player,
+    renderer: Renderer,
     pub forward: bool,
     pub back: bool,
     pub left: bool,
     pub right: bool,
+    pub turn_left: bool,
+    pub turn_right: bool,
+    pub bindings: Bindings,
+    pub gamepad: Gamepad,
+    pending: Vec<Action>, // One-shot actions waiting for the next tick
+    mouse_turn: f64,
+    mouse_tilt: f64,
+    accumulator: f64, // Time not yet simulated
+    ticks: u64,
+    recording: Option<Recording>,
+    mode: Mode,
+    step: Option<Step>,
+    clock: f64, // Turns taken, weighted by walk cost
+    settings: Settings,
+    frame: RgbaImage, // Reused from frame to frame
 }
 
 impl Game {
@@ -44,241 +77,410 @@ impl Game {
         Game {
             dungeon: dungeon,
             player: Player::new(px, py),
+            renderer: Renderer::new(),
             forward: false,
             back: false,
             left: false,
             right: false,
+            turn_left: false,
+            turn_right: false,
+            bindings: Bindings::default(),
+            gamepad: Gamepad::default(),
+            pending: Vec::new(),
+            mouse_turn: 0.0,
+            mouse_tilt: 0.0,
+            accumulator: 0.0,
+            ticks: 0,
+            recording: None,
+            mode: Mode::RealTime,
+            step: None,
+            clock: 0.0,
+            settings: Settings::default(),
+            frame: RgbaImage::new(1, 1),
         }
     }
-}
 
-impl EventHandler for Game {
-    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
-        let dt = timer::duration_to_f64(dt);
-        let speed = 3.3;
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Changes settings, taking effect from the next tick or frame.
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings.clamped();
+        self.player.fov = self.settings.fov.to_radians();
+        self.player.radius = self.settings.player_radius;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switches movement modes. Going turn-based puts the player in the
+    /// middle of their tile, facing straight down the grid.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.step = None;
+        self.pending.clear();
+
+        if mode == Mode::TurnBased {
+            self.player.x_pos = self.player.x_pos.floor() + 0.5;
+            self.player.y_pos = self.player.y_pos.floor() + 0.5;
+            self.player.angle = wrap_angle((self.player.angle / (PI / 2.0)).round() * (PI / 2.0));
+        }
+    }
+
+    /// The world clock, in turns. It only moves in turn-based mode.
+    pub fn clock(&self) -> f64 {
+        self.clock
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    /// The player as drawn. Mid-step in turn-based mode this is part way
+    /// between the last tile and heading and the new ones.
+    pub fn view(&self) -> Player {
+        let mut view = self.player.clone();
+        if let Some(ref step) = self.step {
+            let t = step.progress;
+            view.x_pos = step.from.0 + (step.to.0 - step.from.0) * t;
+            view.y_pos = step.from.1 + (step.to.1 - step.from.1) * t;
+            view.angle = wrap_angle(step.from.2 + (step.to.2 - step.from.2) * t);
+        }
+
+        view
+    }
+
+    /// Number of ticks run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Starts saving every input from here on. `seed`, `generator` and
+    /// `params` have to be the ones the dungeon was made from.
+    pub fn start_recording(&mut self, seed: &str, generator: &str, params: &DungeonParams) {
+        self.recording = Some(Recording::new(seed, generator, params, self));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        let ticks = self.ticks;
+        self.recording.take().map(|mut recording| {
+            recording.ticks = ticks;
+            recording
+        })
+    }
+
+    /// Runs as many ticks as fit into `dt` seconds plus whatever was left
+    /// over last time, and returns how many that was.
+    pub fn advance(&mut self, dt: f64) -> u32 {
+        self.accumulator += dt.min(MAX_FRAME_TIME);
+
+        let mut ticks = 0;
+        while self.accumulator >= TICK {
+            self.tick();
+            self.accumulator -= TICK;
+            ticks += 1;
+        }
+
+        ticks
+    }
+
+    /// Advances the simulation by one `TICK`, applying everything that was
+    /// pressed or held since the last one.
+    pub fn tick(&mut self) {
+        let dt = TICK;
+        self.ticks += 1;
+
+        let (turn, tilt) = self.gamepad.look(dt);
+        let turn = turn + mem::replace(&mut self.mouse_turn, 0.0);
+        let tilt = tilt + mem::replace(&mut self.mouse_tilt, 0.0);
+        let turn = if self.settings.invert_x { -turn } else { turn };
+        let tilt = if self.settings.invert_y { -tilt } else { tilt };
+        self.player.look(tilt);
+
+        match self.mode {
+            Mode::RealTime => {
+                self.player.rotate(turn);
+                self.move_real_time(dt);
+            },
+            Mode::TurnBased => self.move_turn_based(dt),
+        }
+
+        self.player.update_height(dt);
+    }
+
+    fn move_real_time(&mut self, dt: f64) {
+        for action in mem::replace(&mut self.pending, Vec::new()) {
+            match action {
+                Action::Use => self.use_door(),
+                Action::Jump => self.player.jump(),
+                _ => {},
+            }
+        }
+
+        if self.turn_left {
+            self.player.rotate(-self.gamepad.look_speed * dt);
+        } else if self.turn_right {
+            self.player.rotate(self.gamepad.look_speed * dt);
+        }
 
         let cur_x = self.player.x_pos;
         let cur_y = self.player.y_pos;
 
-        let angle_x = self.player.angle.cos();
-        let angle_y = self.player.angle.sin();
+        let walk_cost = self.dungeon.grid.get(cur_x.floor() as u32, cur_y.floor() as u32)
+            .and_then(|tile| tile.material())
+            .map_or(1.0, |material| material.walk_cost);
+        let mut speed = 3.3 / walk_cost;
+        if self.player.crouching {
+            speed *= 0.5;
+        }
 
-        let mut x_dist = 0.0;
-        let mut y_dist = 0.0;
+        // The stick can ask for less than full speed, the keys can't
+        let (stick_x, stick_y) = self.gamepad.movement();
+        let mut forward = -stick_y;
+        let mut strafe = stick_x;
 
         if self.forward {
-            x_dist += angle_x;
-            y_dist += angle_y;
+            forward += 1.0;
         } else if self.back {
-            x_dist -= angle_x;
-            y_dist -= angle_y;
+            forward -= 1.0;
         }
 
-        if self.left {
-            x_dist += angle_y;
-            y_dist -= angle_x;
-        } else if self.right {
-            x_dist -= angle_y;
-            y_dist += angle_x;
+        if self.right {
+            strafe += 1.0;
+        } else if self.left {
+            strafe -= 1.0;
         }
 
+        let angle_x = self.player.angle.cos();
+        let angle_y = self.player.angle.sin();
+
+        let x_dist = forward * angle_x - strafe * angle_y;
+        let y_dist = forward * angle_y + strafe * angle_x;
+
         let length = (x_dist.powi(2) + y_dist.powi(2)).sqrt();
-        let mut nx: f64 = x_dist / length;
-        let mut ny: f64 = y_dist / length;
-        if nx.is_nan() {
-            nx = 0.0;
+        let scale = if length > 1.0 { 1.0 / length } else { 1.0 };
+
+        let dx = x_dist * scale * speed * dt;
+        let dy = y_dist * scale * speed * dt;
+
+        let (x, y) = collision::slide(&self.dungeon.grid, cur_x, cur_y, dx, dy, self.player.radius);
+        self.player.x_pos = x;
+        self.player.y_pos = y;
+    }
+
+    fn move_turn_based(&mut self, dt: f64) {
+        if let Some(mut step) = self.step.take() {
+            step.progress = (step.progress + dt / step.duration).min(1.0);
+            if step.progress < 1.0 {
+                self.step = Some(step);
+            }
+            return;
         }
 
-        if ny.is_nan() {
-            ny = 0.0;
+        // Presses that came in during the last step go first, then anything
+        // still held down
+        let action = if !self.pending.is_empty() {
+            Some(self.pending.remove(0))
+        } else if self.forward {
+            Some(Action::Forward)
+        } else if self.back {
+            Some(Action::Back)
+        } else if self.left {
+            Some(Action::Left)
+        } else if self.right {
+            Some(Action::Right)
+        } else if self.turn_left {
+            Some(Action::TurnLeft)
+        } else if self.turn_right {
+            Some(Action::TurnRight)
+        } else {
+            None
+        };
+
+        if let Some(action) = action {
+            self.take_turn(action);
         }
+    }
+
+    // Starts the step for one turn-based action. Walking into something
+    // solid doesn't use up a turn.
+    fn take_turn(&mut self, action: Action) {
+        let (x, y, angle) = (self.player.x_pos, self.player.y_pos, self.player.angle);
+        let (fx, fy) = (angle.cos().round(), angle.sin().round());
+
+        let (to, duration, cost) = match action {
+            Action::Forward | Action::Back | Action::Left | Action::Right => {
+                let (dx, dy) = match action {
+                    Action::Forward => (fx, fy),
+                    Action::Back => (-fx, -fy),
+                    Action::Left => (fy, -fx),
+                    _ => (-fy, fx),
+                };
+
+                let (tx, ty) = (x + dx, y + dy);
+                if tx < 0.0 || ty < 0.0 {
+                    return;
+                }
 
-        let mut dx = nx * speed * dt;
-        let mut dy = ny * speed * dt;
+                let cost = match self.dungeon.grid.get(tx as u32, ty as u32) {
+                    Some(tile) if !tile.is_solid() => tile.material().map_or(1.0, |m| m.walk_cost),
+                    _ => return,
+                };
 
-        match self.dungeon.grid.get((cur_x + dx).floor() as u32, cur_y.floor() as u32) {
-            Some(&Tile::Wall(_)) | None => {
-                dx = 0.0;
+                ((tx, ty, angle), STEP_TIME * cost, cost)
+            },
+            Action::TurnLeft => ((x, y, angle - PI / 2.0), TURN_TIME, 1.0),
+            Action::TurnRight => ((x, y, angle + PI / 2.0), TURN_TIME, 1.0),
+            Action::Use => {
+                self.use_door();
+                self.clock += 1.0;
+                return;
             },
-            _ => {},
+            Action::Jump | Action::Crouch => return,
+        };
+
+        self.clock += cost;
+        self.player.x_pos = to.0;
+        self.player.y_pos = to.1;
+        self.player.angle = wrap_angle(to.2);
+        self.step = Some(Step {
+            from: (x, y, angle),
+            to: to,
+            duration: duration,
+            progress: 0.0,
+        });
+    }
+
+    /// Feeds one input to the simulation. It takes effect on the next tick.
+    pub fn input(&mut self, event: Event) {
+        if let Some(ref mut recording) = self.recording {
+            recording.events.push((self.ticks, event));
         }
 
-        match self.dungeon.grid.get(cur_x.floor() as u32, (cur_y + dy).floor() as u32) {
-            Some(&Tile::Wall(_)) | None => {
-                dy = 0.0;
+        match event {
+            Event::Press(action) => {
+                match action {
+                    Action::Forward => self.forward = true,
+                    Action::Back => self.back = true,
+                    Action::Left => self.left = true,
+                    Action::Right => self.right = true,
+                    Action::TurnLeft => self.turn_left = true,
+                    Action::TurnRight => self.turn_right = true,
+                    Action::Crouch => self.player.crouching = true,
+                    Action::Use | Action::Jump => {},
+                }
+
+                // A quick tap in turn-based mode still gets its turn, but
+                // don't let more than a couple of them stack up
+                let queued = match action {
+                    Action::Use | Action::Jump => true,
+                    Action::Crouch => false,
+                    _ => self.mode == Mode::TurnBased,
+                };
+                if queued && self.pending.len() < 2 {
+                    self.pending.push(action);
+                }
+            },
+            Event::Release(action) => match action {
+                Action::Forward => self.forward = false,
+                Action::Back => self.back = false,
+                Action::Left => self.left = false,
+                Action::Right => self.right = false,
+                Action::TurnLeft => self.turn_left = false,
+                Action::TurnRight => self.turn_right = false,
+                Action::Crouch => self.player.crouching = false,
+                _ => {},
             },
-            _ => {},
+            Event::Look(turn, tilt) => {
+                self.mouse_turn += turn;
+                self.mouse_tilt += tilt;
+            },
+            Event::Stick(axis, value) => self.gamepad.axis_event(axis, value),
+        }
+    }
+
+    /// Starts an action. Key repeats don't do anything.
+    pub fn press(&mut self, action: Action, repeat: bool) {
+        if !repeat {
+            self.input(Event::Press(action));
+        }
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.input(Event::Release(action));
+    }
+
+    /// Opens or closes the door the player is facing, if there is one.
+    pub fn use_door(&mut self) {
+        let tx = (self.player.x_pos + self.player.angle.cos()).floor();
+        let ty = (self.player.y_pos + self.player.angle.sin()).floor();
+
+        if tx < 0.0 || ty < 0.0 {
+            return;
+        }
+
+        // Don't shut a door on the player
+        let near_x = self.player.x_pos.max(tx).min(tx + 1.0);
+        let near_y = self.player.y_pos.max(ty).min(ty + 1.0);
+        if (self.player.x_pos - near_x).hypot(self.player.y_pos - near_y) < self.player.radius {
+            return;
         }
 
-        self.player.x_pos += dx;
-        self.player.y_pos += dy;
+        let toggled = match self.dungeon.grid.get_mut(tx as u32, ty as u32) {
+            Some(&mut Tile::Door(ref mut door)) => door.toggle(),
+            _ => false,
+        };
 
+        if toggled {
+            self.dungeon.relight();
+        }
+    }
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::RealTime
+    }
+}
+
+impl EventHandler for Game {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        self.advance(timer::duration_to_f64(dt));
         Ok(())
     }
 
     fn draw(&mut self, mut ctx: &mut Context) -> GameResult<()> {
         let (screen_w, screen_h) = ctx.gfx_context.get_drawable_size();
-        let proj_dist =
-            (screen_w as f64 / 2.0) / (self.player.fov / 2.0).tan();
-
-        let mut buffer = RgbaImage::from_pixel(screen_w, screen_h, &RED);
-
-        for x in 0..screen_w {
-            let ray_screen_x = x as f64 - screen_w as f64 / 2.0;
-            let ray_view_dist =
-                (ray_screen_x.powi(2) + proj_dist.powi(2)).sqrt();
-            let ray_angle: f64 =
-                (ray_screen_x / ray_view_dist).asin()+self.player.angle;
-
-            let mut int_dist: f64 = 0.0;
-            let mut int_x: f64 = 0.0;
-            let mut int_y: f64 = 0.0;
-            let mut cell_x: u32 = 0;
-            let mut cell_y: u32 = 0;
-            let mut cell_edge = false; // false for y, true for x
-
-            let cell_size: f64 = 1.0;
-            let angle = wrap_angle(ray_angle);
-            let is_ray_right: bool =
-                angle > (TAU * 0.75) || angle < (TAU * 0.25);
-            let is_ray_up: bool =
-                angle < 0.0 || angle > f64::consts::PI;
-
-            {
-                let mut slope = angle.sin() / angle.cos();
-                let mut dx =
-                    if is_ray_right { cell_size } else { -cell_size};
-                let mut dy = dx * slope;
-
-                let mut ray_position_x =
-                    if is_ray_right {
-                        f64::ceil(self.player.x_pos)
-                    } else {
-                        f64::floor(self.player.x_pos)
-                    };
-                let mut ray_position_y =
-                    self.player.y_pos
-                    + (ray_position_x - self.player.x_pos) * slope;
-
-                while ray_position_x >= 0.0
-                    && ray_position_x <= self.dungeon.grid.width() as f64
-                    && ray_position_y >= 0.0
-                    && ray_position_y <= self.dungeon.grid.height()
-                    as f64 {
-                        let tile_map_x =
-                            f64::floor(ray_position_x + (if is_ray_right { 0.0 } else { -cell_size })) as u32;
-                        let tile_map_y =
-                            f64::floor(ray_position_y) as u32;
-
-                        match self.dungeon.grid.get(tile_map_x, tile_map_y) {
-                            Some(&Tile::Wall(_)) | None => {
-                                let dist_x = ray_position_x - self.player.x_pos;
-                                let dist_y = ray_position_y - self.player.y_pos;
-                                int_dist = dist_x.powi(2) + dist_y.powi(2);
-
-                                cell_edge = false;
-
-                                cell_x = tile_map_x;
-                                cell_y = tile_map_y;
-
-                                int_x = ray_position_x;
-                                int_y = ray_position_y;
-
-                                break;
-                            },
-                            _ => {},
-                        }
-
-                        ray_position_x += dx;
-                        ray_position_y += dy;
-                }
-            }
-
-            {
-                let slope = angle.cos() / angle.sin();
-                let delta_y = if is_ray_up { -cell_size } else { cell_size };
-                let delta_x = delta_y * slope;
-
-                let mut ray_position_y = if is_ray_up { f64::floor(self.player.y_pos) } else { f64::ceil(self.player.y_pos) };
-                let mut ray_position_x = self.player.x_pos + (ray_position_y - self.player.y_pos) * slope;
-
-                while (ray_position_x >= 0.0) && (ray_position_x < self.dungeon.grid.width() as f64) && (ray_position_y >= 0.0) && (ray_position_y < self.dungeon.grid.height() as f64) {
-                    let tile_map_x: u32 = f64::floor(ray_position_x) as u32;
-                    let tile_map_y: u32 = f64::floor(ray_position_y + (if is_ray_up { -cell_size } else { 0.0 })) as u32;
-
-                    match self.dungeon.grid.get(tile_map_x, tile_map_y) {
-                        Some(&Tile::Wall(_)) | None => {
-                            let distance_x: f64 = ray_position_x - self.player.x_pos;
-                            let distance_y: f64 = ray_position_y - self.player.y_pos;
-                            let x_intersection_distance = distance_x.powi(2) + distance_y.powi(2);
-                            if (int_dist == 0.0) || (x_intersection_distance < int_dist) {
-                                int_dist = x_intersection_distance;
-                                cell_edge = true;
-                                cell_x = tile_map_x;
-                                cell_y = tile_map_y;
-                                int_x = ray_position_x;
-                                int_y = ray_position_y;
-                            }
-
-                            break;
-                        },
-                        _ => {},
-                    }
-
-                    ray_position_x += delta_x;
-                    ray_position_y += delta_y;
-                }
-
-                let actual_dist = int_dist.sqrt() * (self.player.angle - ray_angle).cos();
-                let line_height: i32 = (proj_dist / actual_dist).round() as i32;
-                let mut line_bottom: i32 = ((screen_h as i32 / 2) - (line_height as i32 / 2));
-                let mut line_top: i32 = line_bottom + line_height;
-
-                let texture = match self.dungeon.grid.get(cell_x, cell_y) {
-                    Some(&Tile::Wall(id)) => {
-                        self.dungeon.grid.texture(id)
-                    },
-                    _ => { unreachable!(); },
-                }.unwrap();
-
-                // let color = if cell_edge {
-                //     *LIGHT_GRAY
-                // } else {
-                //     *DARK_GRAY
-                // };
-
-                let ray_dir_x = self.player.angle.sin();
-                let ray_dir_y = self.player.angle.cos();
-
-                let tex_x: u32 = if cell_edge {
-                    (((int_x - cell_x as f64) % 1.0) * (texture.width()  as f64 - 1.0).round()) as u32
-                } else {
-                    (((int_y - cell_y as f64) % 1.0) * (texture.width()  as f64 - 1.0).round()) as u32
-                };
-
-                for y in 0..screen_h {
-                    if y as i32 >= line_bottom && (y as i32) < line_top {
-                        let line_y = y as i32 - line_bottom;
-                        let tex_y = ((line_y as f64 / line_height as f64) * (texture.height() as f64- 1.0)).floor() as u32;
-                        buffer.set(x as u32, y as u32, &texture.get(tex_x, tex_y));
-                    }
-                }
-            }
+        let (view_w, view_h) = self.settings.render_size(screen_w, screen_h);
+        if self.frame.dimensions() != (view_w, view_h) {
+            self.frame = RgbaImage::new(view_w, view_h);
         }
+        let view = self.view();
+        self.renderer.render_into(&mut self.frame, &self.dungeon, &view);
 
         graphics::clear(ctx);
         let mut image = graphics::Image::from_rgba8(
             ctx,
-            buffer.width() as u16,
-            buffer.height() as u16,
-            &buffer,
+            view_w as u16,
+            view_h as u16,
+            &self.frame,
         )?;
+        image.set_filter(FilterMode::Nearest);
 
-        graphics::draw(
+        // Stretch a scaled down view back up to fill the window
+        graphics::draw_ex(
             ctx,
             &image,
-            [screen_w as f32 / 2.0, screen_h as f32 / 2.0].into(),
-            0.0,
+            DrawParam {
+                dest: Point::new(screen_w as f32 / 2.0, screen_h as f32 / 2.0),
+                scale: Point::new(
+                    screen_w as f32 / view_w as f32,
+                    screen_h as f32 / view_h as f32,
+                ),
+                ..Default::default()
+            },
         )?;
 
         graphics::circle(
@@ -291,55 +493,63 @@ impl EventHandler for Game {
 
         graphics::present(&mut ctx);
 
-        timer::sleep_until_next_frame(&ctx, FPS);
+        if self.settings.fps_cap > 0 {
+            timer::sleep_until_next_frame(&ctx, self.settings.fps_cap);
+        }
         Ok(())
     }
 
-    fn key_down_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
-        match keycode {
-            Keycode::W => {
-                self.forward = true;
-            },
-            Keycode::A => {
-                self.left = true;
-            },
-            Keycode::S => {
-                self.back = true;
-            },
-            Keycode::D => {
-                self.right = true;
-            },
-            _ => {},
+    fn key_down_event(&mut self, keycode: Keycode, _keymod: Mod, repeat: bool) {
+        if let Some(action) = self.bindings.action(Input::Key(keycode)) {
+            self.press(action, repeat);
         }
     }
 
     fn key_up_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
-        match keycode {
-            Keycode::W => {
-                self.forward = false;
-            },
-            Keycode::A => {
-                self.left = false;
-            },
-            Keycode::S => {
-                self.back = false;
-            },
-            Keycode::D => {
-                self.right = false;
-            },
-            _ => {},
+        if let Some(action) = self.bindings.action(Input::Key(keycode)) {
+            self.release(action);
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, button: MouseButton, _x: i32, _y: i32) {
+        if let Some(action) = self.bindings.action(Input::Mouse(button)) {
+            self.press(action, false);
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, _x: i32, _y: i32) {
+        if let Some(action) = self.bindings.action(Input::Mouse(button)) {
+            self.release(action);
         }
     }
 
+    fn controller_button_down_event(&mut self, button: Button, _instance_id: i32) {
+        if let Some(action) = self.bindings.action(Input::Pad(button)) {
+            self.press(action, false);
+        }
+    }
+
+    fn controller_button_up_event(&mut self, button: Button, _instance_id: i32) {
+        if let Some(action) = self.bindings.action(Input::Pad(button)) {
+            self.release(action);
+        }
+    }
+
+    fn controller_axis_event(&mut self, axis: Axis, value: i16, _instance_id: i32) {
+        self.input(Event::Stick(axis, value));
+    }
+
     fn mouse_motion_event(
         &mut self,
         _state: MouseState,
         _x: i32,
         _y: i32,
         xrel: i32,
-        _yrel: i32,
+        yrel: i32,
     ) {
-        self.player.rotate(xrel as f64 * 0.5);
+        // Moving the mouse up is a negative yrel
+        let sensitivity = self.settings.mouse_sensitivity;
+        self.input(Event::Look(xrel as f64 * sensitivity, -yrel as f64 * sensitivity));
     }
 
     // fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
@@ -348,13 +558,153 @@ impl EventHandler for Game {
     // }
 }
 
-fn wrap_angle(angle: f64) -> f64 {
-    if angle < 0.0 {
-        return angle + TAU;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::{Grid, Material};
+
+    fn game() -> Game {
+        Game::new(Dungeon::new_from_seed("game"))
+    }
+
+    #[test]
+    fn left_stick_moves_the_player() {
+        let mut game = game();
+        let (x, y) = (game.player.x_pos, game.player.y_pos);
+
+        // Inside the dead zone
+        game.controller_axis_event(Axis::LeftY, -2000, 0);
+        game.advance(0.1);
+        assert_eq!((game.player.x_pos, game.player.y_pos), (x, y));
+
+        game.controller_axis_event(Axis::LeftY, -32768, 0);
+        game.advance(0.1);
+        assert!(game.player.x_pos > x);
+        assert_eq!(game.player.y_pos, y);
+    }
+
+    #[test]
+    fn right_stick_turns_the_player() {
+        let mut game = game();
+        let angle = game.player.angle;
+
+        game.controller_axis_event(Axis::RightX, 32767, 0);
+        game.advance(0.1);
+        assert!(game.player.angle > angle);
+
+        game.controller_axis_event(Axis::RightX, 0, 0);
+        game.controller_axis_event(Axis::RightY, -32768, 0);
+        game.advance(0.1);
+        assert!(game.player.pitch > 0.0);
+    }
+
+    #[test]
+    fn pad_buttons_trigger_actions() {
+        let mut game = game();
+
+        game.controller_button_down_event(Button::B, 0);
+        assert!(game.player.crouching);
+        game.controller_button_up_event(Button::B, 0);
+        assert!(!game.player.crouching);
     }
-    else if angle >= TAU {
-        return angle - TAU;
+
+    #[test]
+    fn ticks_do_not_depend_on_frame_rate() {
+        let mut slow = game();
+        let mut fast = game();
+        slow.press(Action::Forward, false);
+        fast.press(Action::Forward, false);
+        slow.controller_axis_event(Axis::RightX, 20000, 0);
+        fast.controller_axis_event(Axis::RightX, 20000, 0);
+
+        let slow_ticks: u32 = (0..20).map(|_| slow.advance(0.051)).sum();
+        let fast_ticks: u32 = (0..40).map(|_| fast.advance(0.0255)).sum();
+
+        assert_eq!(slow_ticks, 61);
+        assert_eq!(fast_ticks, 61);
+        assert_eq!(slow.player.x_pos, fast.player.x_pos);
+        assert_eq!(slow.player.y_pos, fast.player.y_pos);
+        assert_eq!(slow.player.angle, fast.player.angle);
     }
 
-    angle
+    #[test]
+    fn one_shot_actions_wait_for_a_tick() {
+        let mut game = game();
+
+        game.press(Action::Jump, false);
+        assert_eq!(game.player.z_vel, 0.0);
+
+        game.tick();
+        assert!(game.player.z > game.player.ground_height());
+    }
+
+    #[test]
+    fn turn_based_moves_one_tile_at_a_time() {
+        // A corridor of stone, then a tile of rubble
+        let mut grid = Grid::new(6, 3);
+        for x in 1..4 {
+            grid[(x, 1)] = Tile::Floor(Material::new(0, 0, 0.35, 1.0));
+        }
+        grid[(4, 1)] = Tile::Floor(Material::new(0, 0, 0.2, 1.6));
+
+        let mut game = Game::new(Dungeon::from_grid(grid, (2.5, 1.5)));
+        game.set_mode(Mode::TurnBased);
+
+        game.press(Action::Forward, false);
+        game.release(Action::Forward);
+        game.advance(0.1);
+        assert_eq!((game.player.x_pos, game.player.y_pos), (3.5, 1.5));
+        assert!(game.view().x_pos > 2.5 && game.view().x_pos < 3.5);
+
+        game.advance(1.0);
+        assert_eq!(game.view().x_pos, 3.5);
+        assert_eq!((game.player.x_pos, game.player.y_pos), (3.5, 1.5));
+        assert_eq!(game.clock(), 1.0);
+
+        // Nothing held, so the clock stays put
+        game.advance(1.0);
+        assert_eq!(game.clock(), 1.0);
+
+        // Rough ground costs more
+        game.press(Action::Forward, false);
+        game.release(Action::Forward);
+        game.advance(1.0);
+        game.advance(1.0);
+        assert_eq!((game.player.x_pos, game.player.y_pos), (4.5, 1.5));
+        assert_eq!(game.clock(), 2.6);
+    }
+
+    #[test]
+    fn turn_based_turns_a_quarter_at_a_time() {
+        let mut game = game();
+        game.set_mode(Mode::TurnBased);
+
+        game.press(Action::TurnRight, false);
+        game.release(Action::TurnRight);
+        game.advance(0.05);
+        assert!((game.player.angle - PI / 2.0).abs() < 1e-9);
+        assert!(game.view().angle > 0.0 && game.view().angle < PI / 2.0);
+
+        game.advance(0.5);
+        assert!((game.view().angle - PI / 2.0).abs() < 1e-9);
+        assert_eq!(game.clock(), 1.0);
+    }
+
+    #[test]
+    fn settings_apply_to_the_player() {
+        let mut game = game();
+        game.set_settings(Settings {
+            fov: 90.0,
+            invert_x: true,
+            player_radius: 0.3,
+            ..Settings::default()
+        });
+        assert!((game.player.fov - 90.0f64.to_radians()).abs() < 1e-9);
+        assert_eq!(game.player.radius, 0.3);
+
+        let angle = game.player.angle;
+        game.input(Event::Look(10.0, 0.0));
+        game.tick();
+        assert!((game.player.angle - (angle - 10.0f64.to_radians() + 2.0 * PI)).abs() < 1e-9);
+    }
 }
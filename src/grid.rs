@@ -1,50 +1,163 @@
 use picto::pixel::Read;
-use picto::buffer::Buffer;
 use picto::color::Rgba;
-use picto::{write, read};
+use picto::write;
 use picto::processing::prelude::*;
 use picto::buffer::Rgba as RgbaImage;
 
 use std::io;
 use std::path::Path;
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+use texture::Textures;
 
 #[derive(Debug)]
 pub struct Grid {
     width: u32,
     height: u32,
     tiles: Vec<Tile>,
-    textures: Vec<RgbaImage>,
+    textures: Arc<Textures>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Tile {
     Wall(usize),
-    Floor,
+    Floor(Material),
+    Door(Door),
+}
+
+/// A door set into a wall. Closed and locked doors are drawn as a thin panel
+/// across the middle of the tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Door {
+    pub state: DoorState,
+    pub texture: usize,
+    pub horizontal: bool, // Panel runs along the x axis
+    pub material: Material, // Floor and ceiling under the door
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorState {
+    Open,
+    Closed,
+    Locked,
+}
+
+/// What a walkable tile is made of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub floor_texture: usize,
+    pub ceiling_texture: usize,
+    pub light: f32, // Ambient level: 0.0 is pitch black, 1.0 is fully lit
+    pub walk_cost: f64, // Multiplier on the time it takes to cross the tile
+}
+
+impl Material {
+    pub fn new(floor_texture: usize, ceiling_texture: usize, light: f32, walk_cost: f64) -> Self {
+        Material {
+            floor_texture: floor_texture,
+            ceiling_texture: ceiling_texture,
+            light: light,
+            walk_cost: walk_cost,
+        }
+    }
+}
+
+impl Tile {
+    pub fn material(&self) -> Option<&Material> {
+        match *self {
+            Tile::Floor(ref material) => Some(material),
+            Tile::Door(ref door) => Some(&door.material),
+            Tile::Wall(_) => None,
+        }
+    }
+
+    /// Whether the tile blocks movement and light.
+    pub fn is_solid(&self) -> bool {
+        match *self {
+            Tile::Wall(_) => true,
+            Tile::Floor(_) => false,
+            Tile::Door(ref door) => door.state != DoorState::Open,
+        }
+    }
+}
+
+impl Door {
+    pub fn new(state: DoorState, horizontal: bool, texture: usize, material: Material) -> Self {
+        Door {
+            state: state,
+            texture: texture,
+            horizontal: horizontal,
+            material: material,
+        }
+    }
+
+    /// Opens a closed door or closes an open one. Returns false if the door
+    /// is locked.
+    pub fn toggle(&mut self) -> bool {
+        self.state = match self.state {
+            DoorState::Open => DoorState::Closed,
+            DoorState::Closed => DoorState::Open,
+            DoorState::Locked => return false,
+        };
+
+        true
+    }
 }
 
 impl Grid {
     pub fn new(w: u32, h: u32) -> Self {
+        Grid::with_textures(w, h, Textures::builtin())
+    }
+
+    pub fn with_textures(w: u32, h: u32, textures: Arc<Textures>) -> Self {
         if w == 0 || h == 0 {
             panic!("Width and height must be greater than 0");
         }
 
-        let wall_bytes = include_bytes!("../resources/Tileable10c.png").to_vec();
-        let wall_image = read::from_memory(wall_bytes).unwrap();
-
+        let wall = textures.id("wall_rock").expect("Missing builtin texture");
         Grid {
             width: w,
             height: h,
-            tiles: vec![Tile::Wall(0); (w * h) as usize],
-            textures: vec![wall_image],
+            tiles: vec![Tile::Wall(wall); (w * h) as usize],
+            textures: textures,
+        }
+    }
+
+    /// Builds a grid from rows of `#` for walls and `.` for `floor`.
+    #[cfg(test)]
+    pub fn from_rows(rows: &[&str], floor: Material) -> Self {
+        let mut grid = Grid::new(rows[0].len() as u32, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '.' {
+                    grid[(x as u32, y as u32)] = Tile::Floor(floor);
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn fill(&mut self, tile: Tile) {
+        for t in &mut self.tiles {
+            *t = tile.clone();
         }
     }
 
     pub fn get(&self, x: u32, y: u32) -> Option<&Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
         self.tiles.get((x + (self.width * y)) as usize)
     }
 
     pub fn get_mut(&mut self, x: u32, y: u32) -> Option<&mut Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
         self.tiles.get_mut((x + (self.width * y)) as usize)
     }
 
@@ -52,10 +165,16 @@ impl Grid {
         self.textures.get(id)
     }
 
+    pub fn textures(&self) -> &Arc<Textures> {
+        &self.textures
+    }
+
+    /// How many of a walkable tile's neighbours are walkable too, doors
+    /// included. The edge of the grid counts as open.
     pub fn openings(&self, x: u32, y: u32) -> Option<u32> {
         self.get(x, y).and_then(|tile| {
             match *tile {
-                Tile::Floor => {
+                Tile::Floor(_) | Tile::Door(_) => {
                     let mut count = 0;
 
                     if x == 0 {
@@ -64,8 +183,8 @@ impl Grid {
                         if x == self.width - 1{
                             count += 1;
                         } else {
-                            if let Tile::Floor = self[(x-1, y)] { count += 1; }
-                            if let Tile::Floor = self[(x+1, y)] { count += 1; }
+                            if self[(x-1, y)].material().is_some() { count += 1; }
+                            if self[(x+1, y)].material().is_some() { count += 1; }
                         }
                     }
 
@@ -75,8 +194,8 @@ impl Grid {
                         if y == self.height - 1{
                             count += 1;
                         } else {
-                            if let Tile::Floor = self[(x, y+1)] { count += 1; }
-                            if let Tile::Floor = self[(x, y-1)] { count += 1; }
+                            if self[(x, y+1)].material().is_some() { count += 1; }
+                            if self[(x, y-1)].material().is_some() { count += 1; }
                         }
                     }
 
@@ -89,23 +208,82 @@ impl Grid {
         })
     }
 
+    /// Groups the walkable tiles into regions that can reach each other,
+    /// largest first. Doors count as walkable whatever their state, since
+    /// they can be opened.
+    pub fn regions(&self) -> Vec<Vec<(u32, u32)>> {
+        let mut seen = vec![false; self.tiles.len()];
+        let mut regions = Vec::new();
+
+        for start in 0..self.tiles.len() {
+            if seen[start] || self.tiles[start].material().is_none() {
+                continue;
+            }
+
+            let mut region = Vec::new();
+            let mut stack = vec![start];
+            seen[start] = true;
+
+            while let Some(i) = stack.pop() {
+                let x = i as u32 % self.width;
+                let y = i as u32 / self.width;
+                region.push((x, y));
+
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+
+                for &(nx, ny) in &neighbours {
+                    if let Some(tile) = self.get(nx, ny) {
+                        let n = (nx + self.width * ny) as usize;
+                        if !seen[n] && tile.material().is_some() {
+                            seen[n] = true;
+                            stack.push(n);
+                        }
+                    }
+                }
+            }
+
+            regions.push(region);
+        }
+
+        regions.sort_by(|a, b| b.len().cmp(&a.len()));
+        regions
+    }
+
+    /// Whether every walkable tile can be reached from every other.
+    pub fn is_connected(&self) -> bool {
+        self.regions().len() <= 1
+    }
+
     pub fn render_image<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write::to_path(path, &self.image());
+
+        Ok(())
+    }
+
+    /// A map of the grid, five pixels to a tile: floor is white, doors are
+    /// brown and walls are black.
+    pub fn image(&self) -> RgbaImage {
         let white = Rgba::new(1.0, 1.0, 1.0, 1.0);
         let black = Rgba::new(0.0, 0.0, 0.0, 1.0);
-        let mut image: Buffer<_, f32, _> = Buffer::from_pixel(self.width, self.height, &black);
+        let brown = Rgba::new(0.6, 0.4, 0.2, 1.0);
+        let mut image = RgbaImage::from_pixel(self.width, self.height, &black);
 
         for x in 0..self.width {
             for y in 0..self.height {
-                if let Tile::Floor = self[(x, y)] {
-                    image.set(x as u32, y as u32, &white);
+                match self[(x, y)] {
+                    Tile::Floor(_) => image.set(x as u32, y as u32, &white),
+                    Tile::Door(_) => image.set(x as u32, y as u32, &brown),
+                    Tile::Wall(_) => {},
                 }
             }
         }
 
-        let resized = image.scale_by::<scaler::Nearest>(5.0);
-        write::to_path(path, &resized);
-
-        Ok(())
+        image.scale_by::<scaler::Nearest>(5.0)
     }
 
     pub fn width(&self) -> u32 {
@@ -135,32 +313,85 @@ impl IndexMut <(u32, u32)> for Grid {
 mod tests {
     use super::*;
 
+    fn floor() -> Tile {
+        Tile::Floor(Material::new(0, 0, 0.35, 1.0))
+    }
+
     #[test]
     fn test_get() {
-        let grid = Grid::new(3, 3, Tile::Wall);
-        assert_eq!(grid.get(1, 1), Some(&Tile::Wall));
-        assert_eq!(grid.get(2, 2), Some(&Tile::Wall));
+        let grid = Grid::new(3, 3);
+        assert!(grid.get(1, 1).unwrap().is_solid());
+        assert!(grid.get(2, 2).unwrap().is_solid());
         assert_eq!(grid.get(2, 3), None);
         assert_eq!(grid.get(3, 2), None);
     }
 
     #[test]
     fn check_openings() {
-        let mut grid = Grid::new(3, 3, Tile::Wall);
+        let mut grid = Grid::new(3, 3);
         assert_eq!(grid.openings(1, 1), None);
 
-        grid[(1, 0)] = Tile::Floor;
-        grid[(1, 1)] = Tile::Floor;
-        grid[(2, 1)] = Tile::Floor;
+        grid[(1, 0)] = floor();
+        grid[(1, 1)] = floor();
+        grid[(2, 1)] = floor();
 
         assert_eq!(grid.openings(1, 1), Some(2));
         assert_eq!(grid.openings(1, 0), Some(1));
         assert_eq!(grid.openings(2, 1), Some(1));
 
-        grid[(1,2)] = Tile::Floor;
+        grid[(1,2)] = floor();
         assert_eq!(grid.openings(1, 1), Some(3));
 
-        grid[(0,0)] = Tile::Floor;
+        grid[(0,0)] = floor();
         assert_eq!(grid.openings(1, 1), Some(3));
+
+        // Doors lead somewhere, open or not
+        let material = Material::new(0, 0, 0.35, 1.0);
+        grid[(0, 1)] = Tile::Door(Door::new(DoorState::Closed, false, 0, material));
+        assert_eq!(grid.openings(1, 1), Some(4));
+        assert_eq!(grid.openings(0, 1), Some(2));
+    }
+
+    #[test]
+    fn find_regions() {
+        let mut grid = Grid::new(5, 3);
+        assert!(grid.regions().is_empty());
+        assert!(grid.is_connected());
+
+        grid[(0, 0)] = floor();
+        grid[(1, 0)] = floor();
+        grid[(4, 2)] = floor();
+        grid[(3, 2)] = floor();
+        grid[(3, 1)] = floor();
+        assert_eq!(grid.regions().len(), 2);
+        assert_eq!(grid.regions()[0].len(), 3);
+        assert!(!grid.is_connected());
+
+        let material = Material::new(0, 0, 0.35, 1.0);
+        grid[(2, 1)] = Tile::Door(Door::new(DoorState::Closed, false, 0, material));
+        grid[(1, 1)] = floor();
+        assert!(grid.is_connected());
+    }
+
+    #[test]
+    fn tile_material() {
+        let rubble = Material::new(0, 0, 0.2, 1.6);
+        assert_eq!(Tile::Wall(0).material(), None);
+        assert_eq!(Tile::Floor(rubble).material(), Some(&rubble));
+    }
+
+    #[test]
+    fn doors_block_until_opened() {
+        let material = Material::new(0, 0, 0.35, 1.0);
+        let mut door = Door::new(DoorState::Closed, true, 0, material);
+        assert!(Tile::Door(door.clone()).is_solid());
+
+        assert!(door.toggle());
+        assert_eq!(door.state, DoorState::Open);
+        assert!(!Tile::Door(door.clone()).is_solid());
+
+        let mut locked = Door::new(DoorState::Locked, true, 0, material);
+        assert!(!locked.toggle());
+        assert!(Tile::Door(locked).is_solid());
     }
 }
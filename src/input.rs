@@ -0,0 +1,314 @@
+use ggez::event::{Axis, Button, Keycode, MouseButton};
+
+use std::collections::BTreeMap;
+
+/// Something the player can do, independent of what it's bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    TurnLeft,
+    TurnRight,
+    Use,
+    Jump,
+    Crouch,
+}
+
+/// A key, mouse button or controller button that can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton),
+    Pad(Button),
+}
+
+/// Analog stick positions from a game controller.
+///
+/// Each stick has a round dead zone around its center; past it the stick
+/// ramps from 0.0 up to 1.0 at full tilt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gamepad {
+    pub deadzone: f64, // Fraction of the stick's travel that's ignored
+    pub look_speed: f64, // Degrees per second with the right stick at full tilt
+    axes: [f64; 4], // Left x, left y, right x, right y, from -1.0 to 1.0
+}
+
+// A dead zone of the whole stick would leave it doing nothing at all
+pub const MAX_DEADZONE: f64 = 0.95;
+
+/// Which inputs trigger each action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    inputs: BTreeMap<Action, Vec<Input>>,
+}
+
+const ACTIONS: &[(Action, &str)] = &[
+    (Action::Forward, "forward"),
+    (Action::Back, "back"),
+    (Action::Left, "left"),
+    (Action::Right, "right"),
+    (Action::TurnLeft, "turn_left"),
+    (Action::TurnRight, "turn_right"),
+    (Action::Use, "use"),
+    (Action::Jump, "jump"),
+    (Action::Crouch, "crouch"),
+];
+
+const MOUSE_BUTTONS: &[(MouseButton, &str)] = &[
+    (MouseButton::Left, "Mouse Left"),
+    (MouseButton::Middle, "Mouse Middle"),
+    (MouseButton::Right, "Mouse Right"),
+    (MouseButton::X1, "Mouse X1"),
+    (MouseButton::X2, "Mouse X2"),
+];
+
+impl Action {
+    pub fn all() -> Vec<Action> {
+        ACTIONS.iter().map(|&(action, _)| action).collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|&&(_, n)| n == name).map(|&(action, _)| action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|&&(action, _)| action == *self).unwrap().1
+    }
+}
+
+impl Input {
+    /// Parses a key name as SDL writes it ("W", "Left Ctrl"), a mouse
+    /// button such as "Mouse Left" or a controller button such as "Pad a".
+    pub fn from_name(name: &str) -> Option<Input> {
+        if name.starts_with("Pad ") {
+            return Button::from_string(&name[4..]).map(Input::Pad);
+        }
+
+        MOUSE_BUTTONS.iter()
+            .find(|&&(_, n)| n == name)
+            .map(|&(button, _)| Input::Mouse(button))
+            .or_else(|| Keycode::from_name(name).map(Input::Key))
+    }
+
+    pub fn name(&self) -> String {
+        match *self {
+            Input::Key(keycode) => keycode.name(),
+            Input::Mouse(button) => MOUSE_BUTTONS.iter()
+                .find(|&&(b, _)| b == button)
+                .map_or("Mouse Unknown".to_string(), |&(_, n)| n.to_string()),
+            Input::Pad(button) => format!("Pad {}", button.string()),
+        }
+    }
+}
+
+impl Gamepad {
+    pub fn new(deadzone: f64, look_speed: f64) -> Self {
+        Gamepad {
+            deadzone: deadzone,
+            look_speed: look_speed,
+            axes: [0.0; 4],
+        }
+    }
+
+    /// The same gamepad with its dead zone and look speed pulled back into
+    /// sensible ranges.
+    pub fn clamped(&self) -> Gamepad {
+        Gamepad {
+            deadzone: self.deadzone.max(0.0).min(MAX_DEADZONE),
+            look_speed: self.look_speed.max(0.0),
+            ..self.clone()
+        }
+    }
+
+    /// Records a stick moving. Triggers are ignored.
+    pub fn axis_event(&mut self, axis: Axis, value: i16) {
+        let i = match axis {
+            Axis::LeftX => 0,
+            Axis::LeftY => 1,
+            Axis::RightX => 2,
+            Axis::RightY => 3,
+            _ => return,
+        };
+
+        self.axes[i] = (value as f64 / 32767.0).max(-1.0);
+    }
+
+    /// Left stick position, with y negative when pushed forward.
+    pub fn movement(&self) -> (f64, f64) {
+        self.stick(0)
+    }
+
+    /// Degrees to turn and to tilt the view over `dt` seconds.
+    pub fn look(&self, dt: f64) -> (f64, f64) {
+        let (x, y) = self.stick(2);
+        (x * self.look_speed * dt, -y * self.look_speed * dt)
+    }
+
+    fn stick(&self, i: usize) -> (f64, f64) {
+        let (x, y) = (self.axes[i], self.axes[i + 1]);
+        let length = (x.powi(2) + y.powi(2)).sqrt();
+        if length <= self.deadzone {
+            return (0.0, 0.0);
+        }
+
+        let scale = ((length - self.deadzone) / (1.0 - self.deadzone)).min(1.0) / length;
+        (x * scale, y * scale)
+    }
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Gamepad::new(0.2, 180.0)
+    }
+}
+
+impl Bindings {
+    /// No bindings at all.
+    pub fn empty() -> Self {
+        Bindings {
+            inputs: BTreeMap::new(),
+        }
+    }
+
+    /// Loads bindings from action and input names, as stored in the config
+    /// file. Actions that aren't listed keep their default bindings.
+    pub fn from_names(names: &BTreeMap<String, Vec<String>>) -> Result<Self, String> {
+        let mut bindings = Bindings::default();
+
+        for (action_name, input_names) in names {
+            let action = Action::from_name(action_name)
+                .ok_or_else(|| format!("unknown action \"{}\"", action_name))?;
+
+            bindings.clear(action);
+            for input_name in input_names {
+                let input = Input::from_name(input_name)
+                    .ok_or_else(|| format!("unknown key \"{}\" for {}", input_name, action_name))?;
+                bindings.bind(input, action);
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn to_names(&self) -> BTreeMap<String, Vec<String>> {
+        self.inputs.iter()
+            .map(|(action, inputs)| {
+                (action.name().to_string(), inputs.iter().map(|input| input.name()).collect())
+            })
+            .collect()
+    }
+
+    /// Binds `input` to `action`, taking it away from any other action.
+    pub fn bind(&mut self, input: Input, action: Action) {
+        for inputs in self.inputs.values_mut() {
+            inputs.retain(|&i| i != input);
+        }
+
+        self.inputs.entry(action).or_insert_with(Vec::new).push(input);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.inputs.remove(&action);
+    }
+
+    pub fn action(&self, input: Input) -> Option<Action> {
+        self.inputs.iter()
+            .find(|&(_, inputs)| inputs.contains(&input))
+            .map(|(&action, _)| action)
+    }
+
+    pub fn inputs(&self, action: Action) -> &[Input] {
+        self.inputs.get(&action).map_or(&[], |inputs| inputs.as_slice())
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Bindings::empty();
+        bindings.bind(Input::Key(Keycode::W), Action::Forward);
+        bindings.bind(Input::Key(Keycode::S), Action::Back);
+        bindings.bind(Input::Key(Keycode::A), Action::Left);
+        bindings.bind(Input::Key(Keycode::D), Action::Right);
+        bindings.bind(Input::Key(Keycode::Left), Action::TurnLeft);
+        bindings.bind(Input::Key(Keycode::Right), Action::TurnRight);
+        bindings.bind(Input::Key(Keycode::E), Action::Use);
+        bindings.bind(Input::Key(Keycode::Space), Action::Jump);
+        bindings.bind(Input::Key(Keycode::LCtrl), Action::Crouch);
+        bindings.bind(Input::Pad(Button::X), Action::Use);
+        bindings.bind(Input::Pad(Button::A), Action::Jump);
+        bindings.bind(Input::Pad(Button::B), Action::Crouch);
+        bindings.bind(Input::Pad(Button::DPadUp), Action::Forward);
+        bindings.bind(Input::Pad(Button::DPadDown), Action::Back);
+        bindings.bind(Input::Pad(Button::DPadLeft), Action::TurnLeft);
+        bindings.bind(Input::Pad(Button::DPadRight), Action::TurnRight);
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_wasd() {
+        let bindings = Bindings::default();
+        assert_eq!(bindings.action(Input::Key(Keycode::W)), Some(Action::Forward));
+        assert_eq!(bindings.action(Input::Key(Keycode::D)), Some(Action::Right));
+        assert_eq!(bindings.action(Input::Key(Keycode::Q)), None);
+
+        for action in Action::all() {
+            assert!(!bindings.inputs(action).is_empty());
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Input::Mouse(MouseButton::Right), Action::Use);
+        bindings.bind(Input::Pad(Button::Y), Action::Forward);
+
+        assert_eq!(Bindings::from_names(&bindings.to_names()), Ok(bindings));
+    }
+
+    #[test]
+    fn rebinding_replaces_defaults() {
+        let mut names = BTreeMap::new();
+        names.insert("forward".to_string(), vec!["Up".to_string(), "Mouse Left".to_string()]);
+        names.insert("use".to_string(), vec!["W".to_string()]);
+
+        let bindings = Bindings::from_names(&names).unwrap();
+        assert_eq!(bindings.action(Input::Key(Keycode::Up)), Some(Action::Forward));
+        assert_eq!(bindings.action(Input::Mouse(MouseButton::Left)), Some(Action::Forward));
+        assert_eq!(bindings.action(Input::Key(Keycode::W)), Some(Action::Use));
+        assert_eq!(bindings.action(Input::Key(Keycode::E)), None);
+
+        names.insert("fly".to_string(), vec![]);
+        assert!(Bindings::from_names(&names).is_err());
+    }
+
+    #[test]
+    fn sticks_have_a_dead_zone() {
+        let mut pad = Gamepad::new(0.2, 90.0);
+
+        pad.axis_event(Axis::LeftX, 3000);
+        pad.axis_event(Axis::LeftY, -3000);
+        assert_eq!(pad.movement(), (0.0, 0.0));
+
+        pad.axis_event(Axis::LeftX, 0);
+        pad.axis_event(Axis::LeftY, -32768);
+        assert_eq!(pad.movement(), (0.0, -1.0));
+
+        pad.axis_event(Axis::RightX, 32767);
+        assert_eq!(pad.look(0.5), (45.0, 0.0));
+    }
+
+    #[test]
+    fn clamped_gamepad() {
+        let pad = Gamepad::new(1.0, -90.0).clamped();
+        assert_eq!(pad, Gamepad::new(MAX_DEADZONE, 0.0));
+        assert_eq!(Gamepad::new(-0.5, 90.0).clamped(), Gamepad::new(0.0, 90.0));
+        assert_eq!(Gamepad::default().clamped(), Gamepad::default());
+    }
+}
@@ -1,13 +1,26 @@
-// extern crate serde;
-// #[macro_use] extern crate serde_derive;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
 // extern crate serde_json;
+extern crate toml;
 
 extern crate ggez;
 extern crate rand;
 extern crate picto;
+extern crate rayon;
 #[macro_use] extern crate lazy_static;
 
+pub mod collision;
+pub mod config;
 pub mod dungeon;
 pub mod grid;
+pub mod input;
+pub mod light;
 pub mod game;
 pub mod player;
+pub mod renderer;
+pub mod replay;
+pub mod settings;
+pub mod sprite;
+pub mod texture;
+
+#[cfg(test)] mod temp_file;
@@ -0,0 +1,200 @@
+use grid::Grid;
+
+/// A point light such as a wall torch or a lantern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64, // Tiles beyond this distance get nothing
+    pub intensity: f32,
+}
+
+/// Light level of every tile in a `Grid`.
+///
+/// Each walkable tile starts at its material's ambient level, and every
+/// `Light` that can see the tile adds to it with a quadratic falloff.
+#[derive(Debug, Clone)]
+pub struct LightMap {
+    width: u32,
+    height: u32,
+    levels: Vec<f32>,
+}
+
+impl Light {
+    pub fn new(x: f64, y: f64, radius: f64, intensity: f32) -> Self {
+        Light {
+            x: x,
+            y: y,
+            radius: radius,
+            intensity: intensity,
+        }
+    }
+
+    pub fn torch(x: f64, y: f64) -> Self {
+        Light::new(x, y, 6.0, 0.9)
+    }
+}
+
+impl LightMap {
+    pub fn new(grid: &Grid, lights: &[Light]) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+
+        let mut levels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                levels.push(grid[(x, y)].material().map_or(0.0, |m| m.light));
+            }
+        }
+
+        let mut map = LightMap {
+            width: width,
+            height: height,
+            levels: levels,
+        };
+
+        for light in lights {
+            map.add(grid, light);
+        }
+
+        map
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        self.levels.get((x + (self.width * y)) as usize).cloned()
+    }
+
+    /// Light level at a point in world space, 0.0 outside the map.
+    pub fn level_at(&self, x: f64, y: f64) -> f32 {
+        if x < 0.0 || y < 0.0 {
+            return 0.0;
+        }
+
+        self.get(x as u32, y as u32).unwrap_or(0.0)
+    }
+
+    /// Adds one more light, e.g. a moving one, on top of the map.
+    pub fn add(&mut self, grid: &Grid, light: &Light) {
+        if light.x < 0.0 || light.y < 0.0 {
+            return;
+        }
+
+        let lx = light.x as u32;
+        let ly = light.y as u32;
+
+        let x1 = (light.x - light.radius).max(0.0) as u32;
+        let y1 = (light.y - light.radius).max(0.0) as u32;
+        let x2 = ((light.x + light.radius) as u32).min(self.width - 1);
+        let y2 = ((light.y + light.radius) as u32).min(self.height - 1);
+
+        for x in x1..x2 + 1 {
+            for y in y1..y2 + 1 {
+                if grid[(x, y)].material().is_none() {
+                    continue;
+                }
+
+                let dx = x as f64 + 0.5 - light.x;
+                let dy = y as f64 + 0.5 - light.y;
+                let dist = (dx.powi(2) + dy.powi(2)).sqrt();
+                if dist >= light.radius {
+                    continue;
+                }
+
+                if !line_of_sight(grid, lx, ly, x, y) {
+                    continue;
+                }
+
+                let falloff = (1.0 - dist / light.radius).powi(2) as f32;
+                let i = (x + (self.width * y)) as usize;
+                self.levels[i] = (self.levels[i] + light.intensity * falloff).min(1.0);
+            }
+        }
+    }
+}
+
+// Bresenham walk between two cells, blocked by walls and closed doors.
+fn line_of_sight(grid: &Grid, x1: u32, y1: u32, x2: u32, y2: u32) -> bool {
+    let (mut x, mut y) = (x1 as i64, y1 as i64);
+    let (x2, y2) = (x2 as i64, y2 as i64);
+
+    let dx = (x2 - x).abs();
+    let dy = -(y2 - y).abs();
+    let sx = if x < x2 { 1 } else { -1 };
+    let sy = if y < y2 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    while x != x2 || y != y2 {
+        match grid.get(x as u32, y as u32) {
+            Some(tile) if !tile.is_solid() => {},
+            _ => return false,
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::{Material, Tile};
+
+    fn dark() -> Tile {
+        Tile::Floor(Material::new(0, 0, 0.0, 1.0))
+    }
+
+    #[test]
+    fn light_falls_off_with_distance() {
+        let mut grid = Grid::new(9, 1);
+        for x in 0..9 {
+            grid[(x, 0)] = dark();
+        }
+
+        let map = LightMap::new(&grid, &[Light::new(0.5, 0.5, 6.0, 1.0)]);
+
+        assert_eq!(map.get(0, 0), Some(1.0));
+        assert!(map.get(1, 0) > map.get(3, 0));
+        assert!(map.get(3, 0).unwrap() > 0.0);
+        assert_eq!(map.get(6, 0), Some(0.0));
+    }
+
+    #[test]
+    fn walls_block_light() {
+        let mut grid = Grid::new(5, 1);
+        for x in 0..5 {
+            grid[(x, 0)] = dark();
+        }
+        grid[(2, 0)] = Tile::Wall(0);
+
+        let map = LightMap::new(&grid, &[Light::new(0.5, 0.5, 6.0, 1.0)]);
+
+        assert!(map.get(1, 0).unwrap() > 0.0);
+        assert_eq!(map.get(2, 0), Some(0.0));
+        assert_eq!(map.get(3, 0), Some(0.0));
+    }
+
+    #[test]
+    fn ambient_light_comes_from_material() {
+        let mut grid = Grid::new(2, 1);
+        grid[(0, 0)] = Tile::Floor(Material::new(0, 0, 0.35, 1.0));
+
+        let map = LightMap::new(&grid, &[]);
+
+        assert_eq!(map.get(0, 0), Some(0.35));
+        assert_eq!(map.get(1, 0), Some(0.0));
+        assert_eq!(map.get(2, 0), None);
+    }
+}
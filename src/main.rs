@@ -4,14 +4,58 @@ use ggez::graphics::{self, Color, FullscreenType};
 use ggez::conf::Conf;
 
 use std::env::args;
+use std::fs;
+use std::io;
+use std::sync::Arc;
 
 extern crate mount_arelle;
-use mount_arelle::dungeon::Dungeon;
+use mount_arelle::dungeon::{self, Dungeon, DungeonParams, GENERATORS};
+use mount_arelle::config::Config;
 use mount_arelle::game::Game;
+use mount_arelle::input::Bindings;
+use mount_arelle::replay::Recording;
+use mount_arelle::texture::Textures;
 
+// Usage: mount_arelle <seed> [--generator <name>] [--record <file>]
+//        mount_arelle --replay <file>
 fn main() {
-    let seed = args().nth(1).expect("No seed specified");
-    let dungeon = Dungeon::new_from_seed(&seed);
+    let args: Vec<String> = args().collect();
+
+    if args.get(1).map_or(false, |arg| arg == "--replay") {
+        let path = args.get(2).expect("No replay file specified");
+        replay(path);
+        return;
+    }
+
+    let seed = args.get(1).expect("No seed specified").clone();
+    let mut generator_name = GENERATORS[0].to_string();
+    let mut record_path = None;
+
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--generator" => generator_name = rest.next().expect("No generator specified").clone(),
+            "--record" => record_path = Some(rest.next().expect("No record file specified").clone()),
+            arg => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    let generator = dungeon::generator(&generator_name).unwrap_or_else(|| {
+        panic!("Unknown generator {}, expected one of: {}", generator_name, GENERATORS.join(", "))
+    });
+
+    let textures = match Textures::load_dir("resources") {
+        Ok(textures) => Arc::new(textures),
+        Err(err) => {
+            eprintln!("Couldn't load textures from resources/: {}", err);
+            Textures::builtin()
+        },
+    };
+    let params = DungeonParams::load_or_default("resources/dungeon.toml").unwrap_or_else(|err| {
+        eprintln!("Couldn't load resources/dungeon.toml: {}", err);
+        DungeonParams::default()
+    });
+    let dungeon = Dungeon::with_generator(&seed, textures, &*generator, &params);
 
     let mut game = Game::new(dungeon);
 
@@ -27,7 +71,64 @@ fn main() {
     let mut ctx = Context::load_from_conf("mtrl", "sector-f", config).unwrap();
     graphics::set_background_color(&mut ctx, Color::new(0.0, 0.0, 0.0, 1.0));
 
+    let config_dir = ctx.filesystem.get_user_config_dir().to_path_buf();
+    let config_path = config_dir.join("config.toml");
+    let user_config = match Config::load(&config_path) {
+        Ok(user_config) => user_config,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            // Write out the defaults so there's something to edit
+            let mut user_config = Config::default();
+            user_config.set_bindings(&Bindings::default());
+            if let Err(err) = fs::create_dir_all(&config_dir).and_then(|_| user_config.save(&config_path)) {
+                eprintln!("Couldn't write {}: {}", config_path.display(), err);
+            }
+            user_config
+        },
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", config_path.display(), err);
+            Config::default()
+        },
+    };
+
+    game.bindings = match user_config.bindings() {
+        Ok(bindings) => bindings,
+        Err(err) => {
+            eprintln!("Bad key bindings in {}: {}", config_path.display(), err);
+            Bindings::default()
+        },
+    };
+    game.gamepad = user_config.gamepad();
+    game.set_mode(user_config.mode);
+    game.set_settings(user_config.settings.clone());
+
+    if record_path.is_some() {
+        game.start_recording(&seed, &generator_name, &params);
+    }
+
     &ctx.sdl_context.mouse().set_relative_mouse_mode(true);
 
     event::run(&mut ctx, &mut game).unwrap();
+
+    if let (Some(path), Some(recording)) = (record_path, game.stop_recording()) {
+        match recording.save(&path) {
+            Ok(()) => println!("Saved {} ticks to {}", recording.ticks, path),
+            Err(err) => eprintln!("Couldn't save {}: {}", path, err),
+        }
+    }
+}
+
+// Plays a recording back without opening a window and prints where the
+// player ended up.
+fn replay(path: &str) {
+    let recording = Recording::load(path).expect("Couldn't load replay");
+    let game = recording.play();
+    let player = game.player();
+
+    println!("seed: {}", recording.seed);
+    println!("generator: {}", recording.generator);
+    println!("ticks: {}", game.ticks());
+    println!("position: {} {}", player.x_pos, player.y_pos);
+    println!("angle: {}", player.angle.to_degrees());
+    println!("pitch: {}", player.pitch.to_degrees());
+    println!("height: {}", player.z);
 }
@@ -1,8 +1,37 @@
+use std::f64::consts::PI;
+
+use light::Light;
+
+/// How far the player can look up or down, in degrees.
+pub const MAX_PITCH: f64 = 30.0;
+
+// Heights are in tiles, with the floor at 0.0 and the ceiling at 1.0
+pub const EYE_HEIGHT: f64 = 0.5;
+pub const CROUCH_HEIGHT: f64 = 0.3;
+/// Default size of the player for collisions, in tiles.
+pub const RADIUS: f64 = 0.2;
+
+const JUMP_SPEED: f64 = 2.5;
+const GRAVITY: f64 = 9.8;
+
+#[derive(Debug, Clone)]
 pub struct Player {
     pub x_pos: f64,
     pub y_pos: f64,
     pub angle: f64,
+    pub pitch: f64, // Radians, positive is looking up
+    pub z: f64, // Eye height above the floor
+    pub z_vel: f64,
+    pub crouching: bool,
+    pub radius: f64, // Size of the player for collisions, in tiles
     pub fov: f64,
+    pub torch: Option<Light>,
+}
+
+/// Wraps an angle in radians into `0..2π`.
+pub fn wrap_angle(angle: f64) -> f64 {
+    let angle = angle % (2.0 * PI);
+    if angle < 0.0 { angle + 2.0 * PI } else { angle }
 }
 
 impl Player {
@@ -11,10 +40,25 @@ impl Player {
             x_pos: x,
             y_pos: y,
             angle: 0.0f64.to_radians(),
+            pitch: 0.0,
+            z: EYE_HEIGHT,
+            z_vel: 0.0,
+            crouching: false,
+            radius: RADIUS,
             fov: 75.0f64.to_radians(),
+            torch: Some(Light::new(x, y, 5.0, 0.7)),
         }
     }
 
+    /// The light the player is carrying, at the player's position.
+    pub fn light(&self) -> Option<Light> {
+        self.torch.as_ref().map(|torch| Light {
+            x: self.x_pos,
+            y: self.y_pos,
+            ..*torch
+        })
+    }
+
     pub fn rotate(&mut self, degrees: f64) {
         let delta = degrees % 360.0;
         let mut new_degrees = self.angle.to_degrees() + delta;
@@ -27,4 +71,96 @@ impl Player {
 
         self.angle = new_degrees.to_radians();
     }
+
+    /// Tilts the view up (positive) or down, clamped to `MAX_PITCH`.
+    pub fn look(&mut self, degrees: f64) {
+        let new_degrees = self.pitch.to_degrees() + degrees;
+        self.pitch = new_degrees.max(-MAX_PITCH).min(MAX_PITCH).to_radians();
+    }
+
+    /// Eye height when standing still, depending on whether the player is
+    /// crouching.
+    pub fn ground_height(&self) -> f64 {
+        if self.crouching { CROUCH_HEIGHT } else { EYE_HEIGHT }
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.z_vel == 0.0 && self.z <= self.ground_height()
+    }
+
+    /// Starts a jump. Does nothing while already in the air.
+    pub fn jump(&mut self) {
+        if self.on_ground() && !self.crouching {
+            self.z_vel = JUMP_SPEED;
+        }
+    }
+
+    /// Applies gravity over `dt` seconds and lands the player on the floor.
+    pub fn update_height(&mut self, dt: f64) {
+        let ground = self.ground_height();
+        if self.on_ground() {
+            self.z = ground;
+            return;
+        }
+
+        self.z_vel -= GRAVITY * dt;
+        self.z += self.z_vel * dt;
+
+        if self.z <= ground {
+            self.z = ground;
+            self.z_vel = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_is_clamped() {
+        let mut player = Player::new(0.0, 0.0);
+
+        player.look(10.0);
+        assert!((player.pitch.to_degrees() - 10.0).abs() < 1e-9);
+
+        player.look(90.0);
+        assert!((player.pitch.to_degrees() - MAX_PITCH).abs() < 1e-9);
+
+        player.look(-200.0);
+        assert!((player.pitch.to_degrees() + MAX_PITCH).abs() < 1e-9);
+    }
+
+    #[test]
+    fn jumps_come_back_down() {
+        let mut player = Player::new(0.0, 0.0);
+        player.jump();
+        player.update_height(0.1);
+        assert!(player.z > EYE_HEIGHT);
+
+        // No jumping again in mid-air
+        let z_vel = player.z_vel;
+        player.jump();
+        assert_eq!(player.z_vel, z_vel);
+
+        for _ in 0..20 {
+            player.update_height(0.1);
+        }
+        assert_eq!(player.z, EYE_HEIGHT);
+        assert!(player.on_ground());
+    }
+
+    #[test]
+    fn crouching_lowers_the_eye() {
+        let mut player = Player::new(0.0, 0.0);
+        player.crouching = true;
+        for _ in 0..10 {
+            player.update_height(0.1);
+        }
+        assert_eq!(player.z, CROUCH_HEIGHT);
+
+        player.crouching = false;
+        player.update_height(0.1);
+        assert_eq!(player.z, EYE_HEIGHT);
+    }
 }
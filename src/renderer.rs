@@ -0,0 +1,758 @@
+use picto::pixel::{Read, Write};
+use picto::buffer::Rgba as RgbaImage;
+use picto::color::Rgba;
+use rayon::prelude::*;
+
+use std::cmp::Ordering;
+use std::f64;
+
+use dungeon::Dungeon;
+use grid::{DoorState, Grid, Material, Tile};
+use light::LightMap;
+use player::{self, Player};
+use sprite::Sprite;
+
+const TAU: f64 = 2.0 * f64::consts::PI;
+
+lazy_static! {
+    static ref MINE_DARK: Rgba = Rgba::new(0.02, 0.02, 0.03, 1.0);
+}
+
+/// Software raycaster for the first-person view.
+///
+/// Rendering only needs a `Dungeon` and a `Player`, so it works without a
+/// window or GPU context (tests, CI, the `render_view` binary).
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub fog: Fog,
+    pub side_shade: f32, // Brightness of walls facing along the y axis
+    pub parallel: bool, // Cast the columns on a thread pool
+}
+
+/// Distance fog. Everything fades linearly into `color` between `start` and
+/// `end` tiles away from the player.
+#[derive(Debug, Clone)]
+pub struct Fog {
+    pub color: Rgba,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Where a ray cast from the player first meets a wall.
+#[derive(Debug, Clone, PartialEq)]
+struct Hit {
+    dist: f64, // Squared distance from the player
+    cell_x: u32,
+    cell_y: u32,
+    int_x: f64,
+    int_y: f64,
+    cell_edge: bool, // false for y, true for x
+}
+
+// What the ray down one column of the view hits.
+struct Column<'a> {
+    view_cos: f64, // Of the ray's angle from the middle of the view
+    ray_cos: f64,
+    ray_sin: f64,
+    wall_dist: f64, // Perpendicular distance to the wall
+    wall_start: u32, // Rows covered by the wall, the rest is floor and ceiling
+    wall_end: u32,
+    slice: Option<Slice<'a>>,
+}
+
+// The part of a wall texture drawn down one column.
+struct Slice<'a> {
+    texture: &'a RgbaImage,
+    tex_x: u32,
+    light: f32,
+    bottom: i32, // Screen row the whole wall starts at, maybe off screen
+    height: i32,
+}
+
+// Everything about the frame that doesn't change from column to column.
+struct View<'a> {
+    grid: &'a Grid,
+    light_map: &'a LightMap,
+    player: &'a Player,
+    proj_dist: f64,
+    horizon: f64, // Screen row of the horizon, moved off center by pitch
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer {
+            fog: Fog::default(),
+            side_shade: 0.6,
+            parallel: true,
+        }
+    }
+
+    pub fn render(
+        &self,
+        dungeon: &Dungeon,
+        player: &Player,
+        width: u32,
+        height: u32,
+    ) -> RgbaImage {
+        let mut buffer = RgbaImage::new(width, height);
+        self.render_into(&mut buffer, dungeon, player);
+        buffer
+    }
+
+    /// Draws the view over the whole of `buffer`, so the same buffer can be
+    /// reused from frame to frame.
+    pub fn render_into(&self, buffer: &mut RgbaImage, dungeon: &Dungeon, player: &Player) {
+        let width = buffer.width();
+        let height = buffer.height();
+
+        // The dungeon's lights don't move, so only the torch is added here
+        let mut light_map = dungeon.light_map().clone();
+        if let Some(torch) = player.light() {
+            light_map.add(&dungeon.grid, &torch);
+        }
+
+        let proj_dist = (width as f64 / 2.0) / (player.fov / 2.0).tan();
+        let view = View {
+            grid: &dungeon.grid,
+            light_map: &light_map,
+            player: player,
+            proj_dist: proj_dist,
+            horizon: height as f64 / 2.0 + proj_dist * player.pitch.tan(),
+        };
+
+        // Each column's ray is cast once, then the rows are drawn straight
+        // into the buffer, each from its own slice of it
+        let cast = |x: u32| self.cast_column(&view, x, width, height);
+        let columns: Vec<Column> = if self.parallel {
+            (0..width).into_par_iter().map(cast).collect()
+        } else {
+            (0..width).map(cast).collect()
+        };
+
+        let draw_row = |(y, row): (usize, &mut [u8])| {
+            for (x, column) in columns.iter().enumerate() {
+                let color = self.draw_pixel(&view, column, y as u32);
+                color.write(&mut row[x * 4..x * 4 + 4]);
+            }
+        };
+
+        let row_len = width as usize * 4; // RGBA, a byte each
+        if self.parallel {
+            buffer.par_chunks_mut(row_len).enumerate().for_each(draw_row);
+        } else {
+            buffer.chunks_mut(row_len).enumerate().for_each(draw_row);
+        }
+
+        let z_buffer: Vec<f64> = columns.iter().map(|column| column.wall_dist).collect();
+        self.draw_sprites(buffer, &view, &dungeon.sprites, &z_buffer);
+    }
+
+    // Casts the ray for column `x` of a view `width` by `height`.
+    fn cast_column<'a>(&self, view: &View<'a>, x: u32, width: u32, height: u32) -> Column<'a> {
+        let player = view.player;
+        let horizon_row = view.horizon.max(0.0).min(height as f64) as u32;
+
+        let ray_screen_x = x as f64 - width as f64 / 2.0;
+        let ray_view_dist =
+            (ray_screen_x.powi(2) + view.proj_dist.powi(2)).sqrt();
+        let ray_angle: f64 =
+            (ray_screen_x / ray_view_dist).asin() + player.angle;
+
+        let mut wall_dist = f64::INFINITY;
+        let (wall_start, wall_end, slice) =
+            match cast_ray(view.grid, player.x_pos, player.y_pos, ray_angle) {
+                Some(hit) => {
+                    wall_dist = hit.dist.sqrt() * (player.angle - ray_angle).cos();
+                    self.wall_slice(view, &hit, ray_angle, wall_dist, height)
+                },
+                None => (horizon_row, horizon_row, None),
+            };
+
+        Column {
+            view_cos: (player.angle - ray_angle).cos(),
+            ray_cos: ray_angle.cos(),
+            ray_sin: ray_angle.sin(),
+            wall_dist: wall_dist,
+            wall_start: wall_start,
+            wall_end: wall_end,
+            slice: slice,
+        }
+    }
+
+    // Works out the wall slice for a column `height` tall, and the rows it
+    // covers. There's no slice if the wall has no texture.
+    fn wall_slice<'a>(
+        &self,
+        view: &View<'a>,
+        hit: &Hit,
+        ray_angle: f64,
+        wall_dist: f64,
+        height: u32,
+    ) -> (u32, u32, Option<Slice<'a>>) {
+        let line_height: i32 = (view.proj_dist / wall_dist).round() as i32;
+        let line_bottom: i32 =
+            (view.horizon - (1.0 - view.player.z) * line_height as f64).round() as i32;
+        let line_top: i32 = line_bottom + line_height;
+
+        let start = if line_bottom < 0 { 0 } else { line_bottom as u32 };
+        let end = if line_top > height as i32 { height } else { line_top.max(0) as u32 };
+        let start = start.min(end);
+
+        let grid = view.grid;
+        let texture = match grid.get(hit.cell_x, hit.cell_y) {
+            Some(&Tile::Wall(id)) => grid.texture(id),
+            Some(&Tile::Door(ref door)) => grid.texture(door.texture),
+            _ => None,
+        };
+
+        let texture = match texture {
+            Some(texture) => texture,
+            None => return (start, end, None),
+        };
+
+        // Walls are lit by the tile the ray crossed to reach them
+        let mut light = view.light_map.level_at(
+            hit.int_x - ray_angle.cos() * 0.01,
+            hit.int_y - ray_angle.sin() * 0.01,
+        );
+
+        if !hit.cell_edge {
+            light *= self.side_shade;
+        }
+
+        let tex_x: u32 = if hit.cell_edge {
+            (((hit.int_x - hit.cell_x as f64) % 1.0) * (texture.width() as f64 - 1.0).round()) as u32
+        } else {
+            (((hit.int_y - hit.cell_y as f64) % 1.0) * (texture.width() as f64 - 1.0).round()) as u32
+        };
+
+        let slice = Slice {
+            texture: texture,
+            tex_x: tex_x,
+            light: light,
+            bottom: line_bottom,
+            height: line_height,
+        };
+
+        (start, end, Some(slice))
+    }
+
+    // The color of row `y` in `column`: the wall slice, or the floor below
+    // it and the ceiling above it.
+    fn draw_pixel(&self, view: &View, column: &Column, y: u32) -> Rgba {
+        if y >= column.wall_start && y < column.wall_end {
+            let slice = match column.slice {
+                Some(ref slice) => slice,
+                None => return self.fog.color,
+            };
+
+            let texture = slice.texture;
+            let line_y = y as i32 - slice.bottom;
+            let tex_y = ((line_y as f64 / slice.height as f64) * (texture.height() as f64 - 1.0)).floor() as u32;
+            let texel = shade(&texture.get(slice.tex_x, tex_y), slice.light);
+            return self.fog.apply(&texel, column.wall_dist);
+        }
+
+        let row = (y as f64 + 0.5) - view.horizon;
+        // The floor is z below the eye and the ceiling is 1 - z above it
+        let eye_dist = if row > 0.0 { view.player.z } else { 1.0 - view.player.z };
+        let plane_dist = eye_dist * view.proj_dist / row.abs();
+        let dist = plane_dist / column.view_cos;
+
+        let floor_x = view.player.x_pos + column.ray_cos * dist;
+        let floor_y = view.player.y_pos + column.ray_sin * dist;
+
+        let material = match material_at(view.grid, floor_x, floor_y) {
+            Some(material) => material,
+            None => return self.fog.color,
+        };
+
+        let texture_id = if row > 0.0 {
+            material.floor_texture
+        } else {
+            material.ceiling_texture
+        };
+
+        match view.grid.texture(texture_id) {
+            Some(texture) => {
+                let tex_x = (floor_x.fract() * texture.width() as f64) as u32;
+                let tex_y = (floor_y.fract() * texture.height() as f64) as u32;
+                let texel = texture.get(
+                    tex_x.min(texture.width() - 1),
+                    tex_y.min(texture.height() - 1),
+                );
+
+                let light = view.light_map.level_at(floor_x, floor_y);
+                let texel = shade(&texel, light);
+                self.fog.apply(&texel, plane_dist)
+            },
+            None => self.fog.color,
+        }
+    }
+
+    // Draws sprites back to front, hiding any column behind a nearer wall.
+    fn draw_sprites(
+        &self,
+        buffer: &mut RgbaImage,
+        view: &View,
+        sprites: &[Sprite],
+        z_buffer: &[f64],
+    ) {
+        let width = buffer.width();
+        let height = buffer.height();
+        let player = view.player;
+
+        let mut visible: Vec<(f64, f64, &Sprite)> = sprites.iter()
+            .filter_map(|sprite| {
+                let dx = sprite.x - player.x_pos;
+                let dy = sprite.y - player.y_pos;
+
+                let mut angle = dy.atan2(dx) - player.angle;
+                while angle > f64::consts::PI { angle -= TAU; }
+                while angle < -f64::consts::PI { angle += TAU; }
+
+                let depth = (dx.powi(2) + dy.powi(2)).sqrt() * angle.cos();
+                if depth < 0.1 {
+                    None
+                } else {
+                    Some((depth, angle, sprite))
+                }
+            })
+            .collect();
+
+        visible.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+        for (depth, angle, sprite) in visible {
+            let texture = match view.grid.texture(sprite.texture) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            let size = view.proj_dist / depth;
+            let center_x = width as f64 / 2.0 + view.proj_dist * angle.tan();
+            let left = center_x - size / 2.0;
+            let top = view.horizon - (1.0 - player.z) * size;
+
+            let x_start = left.max(0.0) as u32;
+            let x_end = (left + size).min(width as f64).max(0.0) as u32;
+            let y_start = top.max(0.0) as u32;
+            let y_end = (top + size).min(height as f64).max(0.0) as u32;
+
+            let light = view.light_map.level_at(sprite.x, sprite.y);
+
+            for x in x_start..x_end {
+                if depth >= z_buffer[x as usize] {
+                    continue;
+                }
+
+                let tex_x = ((x as f64 - left) / size * texture.width() as f64) as u32;
+
+                for y in y_start..y_end {
+                    let tex_y = ((y as f64 - top) / size * texture.height() as f64) as u32;
+                    let texel = texture.get(
+                        tex_x.min(texture.width() - 1),
+                        tex_y.min(texture.height() - 1),
+                    );
+
+                    if texel.alpha < 0.5 {
+                        continue;
+                    }
+
+                    let texel = shade(&texel, light);
+                    buffer.set(x, y, &self.fog.apply(&texel, depth));
+                }
+            }
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new()
+    }
+}
+
+impl Fog {
+    pub fn apply(&self, color: &Rgba, dist: f64) -> Rgba {
+        if dist <= self.start {
+            return *color;
+        } else if dist >= self.end {
+            return self.color;
+        }
+
+        let amount = ((dist - self.start) / (self.end - self.start)) as f32;
+
+        Rgba::new(
+            color.red + (self.color.red - color.red) * amount,
+            color.green + (self.color.green - color.green) * amount,
+            color.blue + (self.color.blue - color.blue) * amount,
+            color.alpha,
+        )
+    }
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            color: *MINE_DARK,
+            start: 1.5,
+            end: 12.0,
+        }
+    }
+}
+
+/// Renders `player`'s view of `dungeon` with the default `Renderer`.
+pub fn render_view(
+    dungeon: &Dungeon,
+    player: &Player,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    Renderer::new().render(dungeon, player, width, height)
+}
+
+fn cast_ray(grid: &Grid, px: f64, py: f64, ray_angle: f64) -> Option<Hit> {
+    let mut hit: Option<Hit> = None;
+
+    let cell_size: f64 = 1.0;
+    let angle = player::wrap_angle(ray_angle);
+    let is_ray_right: bool =
+        angle > (TAU * 0.75) || angle < (TAU * 0.25);
+    let is_ray_up: bool =
+        angle < 0.0 || angle > f64::consts::PI;
+
+    {
+        let slope = angle.sin() / angle.cos();
+        let dx = if is_ray_right { cell_size } else { -cell_size };
+        let dy = dx * slope;
+
+        let mut ray_position_x =
+            if is_ray_right { f64::ceil(px) } else { f64::floor(px) };
+        let mut ray_position_y = py + (ray_position_x - px) * slope;
+
+        while ray_position_x >= 0.0
+            && ray_position_x <= grid.width() as f64
+            && ray_position_y >= 0.0
+            && ray_position_y <= grid.height() as f64 {
+                let tile_map_x =
+                    f64::floor(ray_position_x + (if is_ray_right { 0.0 } else { -cell_size })) as u32;
+                let tile_map_y = f64::floor(ray_position_y) as u32;
+
+                let intersection = match grid.get(tile_map_x, tile_map_y) {
+                    Some(&Tile::Wall(_)) | None => {
+                        Some((ray_position_x, ray_position_y))
+                    },
+                    // Door panels are recessed to the middle of their tile
+                    Some(&Tile::Door(ref door)) if door.state != DoorState::Open && !door.horizontal => {
+                        let half_y = ray_position_y + dy / 2.0;
+                        if f64::floor(half_y) == tile_map_y as f64 {
+                            Some((ray_position_x + dx / 2.0, half_y))
+                        } else {
+                            None
+                        }
+                    },
+                    _ => None,
+                };
+
+                if let Some((int_x, int_y)) = intersection {
+                    let dist_x = int_x - px;
+                    let dist_y = int_y - py;
+
+                    hit = Some(Hit {
+                        dist: dist_x.powi(2) + dist_y.powi(2),
+                        cell_x: tile_map_x,
+                        cell_y: tile_map_y,
+                        int_x: int_x,
+                        int_y: int_y,
+                        cell_edge: false,
+                    });
+
+                    break;
+                }
+
+                ray_position_x += dx;
+                ray_position_y += dy;
+        }
+    }
+
+    {
+        let slope = angle.cos() / angle.sin();
+        let delta_y = if is_ray_up { -cell_size } else { cell_size };
+        let delta_x = delta_y * slope;
+
+        let mut ray_position_y =
+            if is_ray_up { f64::floor(py) } else { f64::ceil(py) };
+        let mut ray_position_x = px + (ray_position_y - py) * slope;
+
+        while (ray_position_x >= 0.0) && (ray_position_x < grid.width() as f64) && (ray_position_y >= 0.0) && (ray_position_y < grid.height() as f64) {
+            let tile_map_x: u32 = f64::floor(ray_position_x) as u32;
+            let tile_map_y: u32 = f64::floor(ray_position_y + (if is_ray_up { -cell_size } else { 0.0 })) as u32;
+
+            let intersection = match grid.get(tile_map_x, tile_map_y) {
+                Some(&Tile::Wall(_)) | None => {
+                    Some((ray_position_x, ray_position_y))
+                },
+                Some(&Tile::Door(ref door)) if door.state != DoorState::Open && door.horizontal => {
+                    let half_x = ray_position_x + delta_x / 2.0;
+                    if f64::floor(half_x) == tile_map_x as f64 {
+                        Some((half_x, ray_position_y + delta_y / 2.0))
+                    } else {
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            if let Some((int_x, int_y)) = intersection {
+                let distance_x: f64 = int_x - px;
+                let distance_y: f64 = int_y - py;
+                let y_intersection_distance = distance_x.powi(2) + distance_y.powi(2);
+
+                let closer = match hit {
+                    Some(ref h) => y_intersection_distance < h.dist,
+                    None => true,
+                };
+
+                if closer {
+                    hit = Some(Hit {
+                        dist: y_intersection_distance,
+                        cell_x: tile_map_x,
+                        cell_y: tile_map_y,
+                        int_x: int_x,
+                        int_y: int_y,
+                        cell_edge: true,
+                    });
+                }
+
+                break;
+            }
+
+            ray_position_x += delta_x;
+            ray_position_y += delta_y;
+        }
+    }
+
+    hit
+}
+
+fn material_at(grid: &Grid, x: f64, y: f64) -> Option<&Material> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+
+    grid.get(x as u32, y as u32).and_then(|tile| tile.material())
+}
+
+fn shade(color: &Rgba, light: f32) -> Rgba {
+    Rgba::new(
+        color.red * light,
+        color.green * light,
+        color.blue * light,
+        color.alpha,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::Door;
+    use texture::Textures;
+
+    // A walled room, seen from near its west wall looking east.
+    const ROOM: &[&str] = &[
+        "########",
+        "#......#",
+        "#......#",
+        "#......#",
+        "########",
+    ];
+
+    fn room() -> Dungeon {
+        let textures = Textures::builtin();
+        let floor = Material::new(
+            textures.id("floor_stone").unwrap(),
+            textures.id("ceiling_rock").unwrap(),
+            0.35,
+            1.0,
+        );
+
+        Dungeon::from_grid(Grid::from_rows(ROOM, floor), (2.5, 2.5))
+    }
+
+    #[test]
+    fn render_has_requested_size() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+
+        let image = render_view(&dungeon, &player, 64, 48);
+        assert_eq!(image.width(), 64);
+        assert_eq!(image.height(), 48);
+    }
+
+    #[test]
+    fn reused_buffers_are_fully_redrawn() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let mut player = Player::new(px, py);
+        let renderer = Renderer::new();
+
+        let mut buffer = RgbaImage::new(64, 48);
+        renderer.render_into(&mut buffer, &dungeon, &player);
+
+        player.rotate(90.0);
+        player.look(15.0);
+        renderer.render_into(&mut buffer, &dungeon, &player);
+
+        assert!(&buffer[..] == &renderer.render(&dungeon, &player, 64, 48)[..]);
+    }
+
+    #[test]
+    fn parallel_rendering_matches_one_thread() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let mut player = Player::new(px, py);
+        player.rotate(30.0);
+        player.look(-10.0);
+
+        let mut renderer = Renderer::new();
+        let parallel = renderer.render(&dungeon, &player, 160, 120);
+        renderer.parallel = false;
+        let single = renderer.render(&dungeon, &player, 160, 120);
+
+        assert!(&parallel[..] == &single[..]);
+    }
+
+    #[test]
+    fn render_draws_walls() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+
+        let image = render_view(&dungeon, &player, 64, 48);
+        let background = RgbaImage::from_pixel(64, 48, &Renderer::new().fog.color);
+        assert!(&image[..] != &background[..]);
+    }
+
+    #[test]
+    fn render_draws_floor_and_ceiling() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+
+        let renderer = Renderer::new();
+        let fog = RgbaImage::from_pixel(1, 1, &renderer.fog.color).get(0, 0);
+        let image = renderer.render(&dungeon, &player, 64, 48);
+        let (top, bottom) = (image.get(32, 0), image.get(32, 47));
+        assert!(top != fog && bottom != fog);
+        assert!(top != bottom);
+
+        // Looking straight ahead, the top and bottom rows meet the ceiling
+        // and floor at the same spot, so swapping their textures swaps them
+        let mut swapped = room();
+        for x in 0..swapped.grid.width() {
+            for y in 0..swapped.grid.height() {
+                if let Tile::Floor(ref mut material) = swapped.grid[(x, y)] {
+                    let floor = material.floor_texture;
+                    material.floor_texture = material.ceiling_texture;
+                    material.ceiling_texture = floor;
+                }
+            }
+        }
+
+        let image = renderer.render(&swapped, &player, 64, 48);
+        assert!(image.get(32, 0) == bottom);
+        assert!(image.get(32, 47) == top);
+    }
+
+    #[test]
+    fn pitch_shifts_the_view() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let mut player = Player::new(px, py);
+        let level = render_view(&dungeon, &player, 64, 48);
+
+        player.look(20.0);
+        let up = render_view(&dungeon, &player, 64, 48);
+        player.look(-40.0);
+        let down = render_view(&dungeon, &player, 64, 48);
+
+        assert!(&up[..] != &level[..]);
+        assert!(&down[..] != &level[..]);
+        assert!(&up[..] != &down[..]);
+    }
+
+    #[test]
+    fn eye_height_moves_the_view() {
+        let dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let mut player = Player::new(px, py);
+        let standing = render_view(&dungeon, &player, 64, 48);
+
+        player.z = 0.8;
+        let jumping = render_view(&dungeon, &player, 64, 48);
+
+        assert!(&jumping[..] != &standing[..]);
+    }
+
+    #[test]
+    fn fog_fades_with_distance() {
+        let fog = Fog::default();
+        let white = Rgba::new(1.0, 1.0, 1.0, 1.0);
+
+        assert_eq!(fog.apply(&white, fog.start), white);
+        assert_eq!(fog.apply(&white, fog.end + 1.0), fog.color);
+
+        let halfway = fog.apply(&white, (fog.start + fog.end) / 2.0);
+        assert!(halfway.red < white.red && halfway.red > fog.color.red);
+    }
+
+    #[test]
+    fn sprites_are_drawn_in_front_of_the_player() {
+        let mut dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+        let without = render_view(&dungeon, &player, 64, 48);
+
+        let chest = dungeon.grid.textures().id("sprite_chest").unwrap();
+        dungeon.sprites.push(Sprite::new(px + 1.5, py, chest));
+        let with = render_view(&dungeon, &player, 64, 48);
+
+        assert!(&with[..] != &without[..]);
+    }
+
+    #[test]
+    fn sprites_behind_the_player_are_skipped() {
+        let mut dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+        let without = render_view(&dungeon, &player, 64, 48);
+
+        let chest = dungeon.grid.textures().id("sprite_chest").unwrap();
+        dungeon.sprites.push(Sprite::new(px - 1.5, py, chest));
+        let with = render_view(&dungeon, &player, 64, 48);
+
+        assert!(&with[..] == &without[..]);
+    }
+
+    #[test]
+    fn closed_doors_are_drawn() {
+        let mut dungeon = room();
+        let (px, py) = dungeon.player_spawn();
+        let player = Player::new(px, py);
+
+        let (dx, dy) = (px as u32 + 2, py as u32);
+        let material = *dungeon.grid[(dx, dy)].material().unwrap();
+        let texture = dungeon.grid.textures().id("door_wood").unwrap();
+        dungeon.grid[(dx, dy)] = Tile::Door(Door::new(DoorState::Open, false, texture, material));
+        let open = render_view(&dungeon, &player, 64, 48);
+
+        if let Tile::Door(ref mut door) = dungeon.grid[(dx, dy)] {
+            door.toggle();
+        }
+        dungeon.relight();
+        let closed = render_view(&dungeon, &player, 64, 48);
+
+        assert!(&open[..] != &closed[..]);
+    }
+}
@@ -0,0 +1,274 @@
+use ggez::event::Axis;
+
+use toml;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use dungeon::{self, Dungeon, DungeonParams, GENERATORS};
+use game::{Game, Mode};
+use input::{Action, Gamepad};
+use player;
+use settings::Settings;
+use texture::Textures;
+
+/// Player input as the simulation sees it, after key bindings have been
+/// applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Press(Action),
+    Release(Action),
+    Look(f64, f64), // Degrees to turn and tilt
+    Stick(Axis, i16),
+}
+
+/// Everything needed to play a run back exactly: the dungeon seed,
+/// generator and params, the settings that affect the simulation, and every
+/// input stamped with the tick it was applied on.
+///
+/// Saved as plain text, one line per entry. The params are the lines of
+/// their TOML file, each after `params`:
+///
+/// ```text
+/// seed hello
+/// generator rooms
+/// mode real_time
+/// invert_x false
+/// invert_y false
+/// player_radius 0.2
+/// deadzone 0.2
+/// look_speed 180
+/// params attempts = [20, 40]
+/// params corridor_width = 1
+/// params [small]
+/// params weight = 300
+/// 12 press forward
+/// 15 look 2.5 -0.5
+/// 40 stick leftx -32768
+/// 90 release forward
+/// 120 end
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub seed: String,
+    pub generator: String,
+    pub params: DungeonParams,
+    pub mode: Mode,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub player_radius: f64,
+    pub deadzone: f64,
+    pub look_speed: f64,
+    pub events: Vec<(u64, Event)>,
+    pub ticks: u64, // How long the run lasted
+}
+
+impl Recording {
+    pub fn new(seed: &str, generator: &str, params: &DungeonParams, game: &Game) -> Self {
+        Recording {
+            seed: seed.to_string(),
+            generator: generator.to_string(),
+            params: params.clone(),
+            mode: game.mode(),
+            invert_x: game.settings().invert_x,
+            invert_y: game.settings().invert_y,
+            player_radius: game.settings().player_radius,
+            deadzone: game.gamepad.deadzone,
+            look_speed: game.gamepad.look_speed,
+            events: Vec::new(),
+            ticks: 0,
+        }
+    }
+
+    /// Builds the recorded dungeon and runs the recorded inputs through it.
+    /// Panics if the generator is unknown.
+    pub fn play(&self) -> Game {
+        let generator = dungeon::generator(&self.generator).expect("Unknown generator");
+        let dungeon = Dungeon::with_generator(&self.seed, Textures::builtin(), &*generator, &self.params);
+
+        let mut game = Game::new(dungeon);
+        game.set_mode(self.mode);
+        game.set_settings(Settings {
+            invert_x: self.invert_x,
+            invert_y: self.invert_y,
+            player_radius: self.player_radius,
+            ..Settings::default()
+        });
+        game.gamepad = Gamepad::new(self.deadzone, self.look_speed).clamped();
+
+        let mut next = 0;
+        for tick in 0..self.ticks {
+            while next < self.events.len() && self.events[next].0 <= tick {
+                game.input(self.events[next].1);
+                next += 1;
+            }
+
+            game.tick();
+        }
+
+        game
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        let file = BufReader::new(File::open(path)?);
+        let mut lines = Vec::new();
+        for line in file.lines() {
+            lines.push(line?);
+        }
+
+        Recording::parse(&lines).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "seed {}", self.seed)?;
+        writeln!(file, "generator {}", self.generator)?;
+        writeln!(file, "mode {}", match self.mode {
+            Mode::RealTime => "real_time",
+            Mode::TurnBased => "turn_based",
+        })?;
+        writeln!(file, "invert_x {}", self.invert_x)?;
+        writeln!(file, "invert_y {}", self.invert_y)?;
+        writeln!(file, "player_radius {}", self.player_radius)?;
+        writeln!(file, "deadzone {}", self.deadzone)?;
+        writeln!(file, "look_speed {}", self.look_speed)?;
+
+        let params = toml::to_string(&self.params)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        for line in params.lines().filter(|line| !line.trim().is_empty()) {
+            writeln!(file, "params {}", line)?;
+        }
+
+        for &(tick, event) in &self.events {
+            match event {
+                Event::Press(action) => writeln!(file, "{} press {}", tick, action.name())?,
+                Event::Release(action) => writeln!(file, "{} release {}", tick, action.name())?,
+                Event::Look(turn, tilt) => writeln!(file, "{} look {} {}", tick, turn, tilt)?,
+                Event::Stick(axis, value) => writeln!(file, "{} stick {} {}", tick, axis.string(), value)?,
+            }
+        }
+
+        writeln!(file, "{} end", self.ticks)
+    }
+
+    fn parse(lines: &[String]) -> Result<Recording, String> {
+        let mut recording = Recording {
+            seed: String::new(),
+            // Recordings from before there was a choice
+            generator: GENERATORS[0].to_string(),
+            params: DungeonParams::default(),
+            mode: Mode::RealTime,
+            invert_x: false,
+            invert_y: false,
+            player_radius: player::RADIUS,
+            deadzone: 0.0,
+            look_speed: 0.0,
+            events: Vec::new(),
+            ticks: 0,
+        };
+
+        // Recordings from before params were saved get the defaults
+        let mut params = String::new();
+
+        for (n, line) in lines.iter().enumerate() {
+            let bad_line = || format!("line {}: can't read \"{}\"", n + 1, line);
+
+            let mut words = line.split_whitespace();
+            let first = match words.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            let args: Vec<&str> = words.collect();
+            let arg = |i: usize| args.get(i).cloned().unwrap_or("");
+
+            match first {
+                "seed" => recording.seed = line["seed".len()..].trim().to_string(),
+                "generator" => recording.generator = arg(0).to_string(),
+                "params" => {
+                    params.push_str(line["params".len()..].trim());
+                    params.push('\n');
+                },
+                "mode" => recording.mode = match arg(0) {
+                    "real_time" => Mode::RealTime,
+                    "turn_based" => Mode::TurnBased,
+                    _ => return Err(bad_line()),
+                },
+                "invert_x" => recording.invert_x = arg(0).parse().map_err(|_| bad_line())?,
+                "invert_y" => recording.invert_y = arg(0).parse().map_err(|_| bad_line())?,
+                "player_radius" => recording.player_radius = arg(0).parse().map_err(|_| bad_line())?,
+                "deadzone" => recording.deadzone = arg(0).parse().map_err(|_| bad_line())?,
+                "look_speed" => recording.look_speed = arg(0).parse().map_err(|_| bad_line())?,
+                tick => {
+                    let tick: u64 = tick.parse().map_err(|_| bad_line())?;
+
+                    let event = match arg(0) {
+                        "end" => {
+                            recording.ticks = tick;
+                            continue;
+                        },
+                        "press" => Action::from_name(arg(1)).map(Event::Press),
+                        "release" => Action::from_name(arg(1)).map(Event::Release),
+                        "look" => match (arg(1).parse(), arg(2).parse()) {
+                            (Ok(turn), Ok(tilt)) => Some(Event::Look(turn, tilt)),
+                            _ => None,
+                        },
+                        "stick" => match (Axis::from_string(arg(1)), arg(2).parse()) {
+                            (Some(axis), Ok(value)) => Some(Event::Stick(axis, value)),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+
+                    recording.events.push((tick, event.ok_or_else(bad_line)?));
+                },
+            }
+        }
+
+        recording.params = DungeonParams::parse(&params).map_err(|err| format!("params: {}", err))?;
+        Ok(recording)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_file::TempFile;
+
+    #[test]
+    fn replays_match_the_original_run() {
+        let seed = "replay";
+        let generator = dungeon::generator("bsp").unwrap();
+        let mut params = DungeonParams::default();
+        params.corridor_width = 2;
+        params.caves.fill = 1.0;
+        let mut game = Game::new(Dungeon::with_generator(seed, Textures::builtin(), &*generator, &params));
+        game.set_settings(Settings { player_radius: 0.3, ..Settings::default() });
+        game.start_recording(seed, "bsp", &params);
+
+        game.press(Action::Forward, false);
+        game.advance(0.3);
+        game.input(Event::Look(45.0, -5.0));
+        game.press(Action::Jump, false);
+        game.advance(0.21);
+        game.input(Event::Stick(Axis::LeftX, 32767));
+        game.release(Action::Forward);
+        game.advance(0.4);
+
+        let recording = game.stop_recording().unwrap();
+        let file = TempFile::new("replay_test.txt");
+        recording.save(file.path()).unwrap();
+        let loaded = Recording::load(file.path()).unwrap();
+        assert_eq!(loaded, recording);
+
+        assert_eq!(loaded.params, params);
+
+        let replayed = loaded.play();
+        assert_eq!(replayed.ticks(), game.ticks());
+        assert_eq!(replayed.player().x_pos, game.player().x_pos);
+        assert_eq!(replayed.player().y_pos, game.player().y_pos);
+        assert_eq!(replayed.player().angle, game.player().angle);
+        assert_eq!(replayed.player().z, game.player().z);
+    }
+}
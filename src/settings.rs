@@ -0,0 +1,91 @@
+use player;
+
+/// Options the player can change, stored under `[settings]` in the config
+/// file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub fov: f64, // Horizontal field of view in degrees
+    pub mouse_sensitivity: f64, // Degrees turned per pixel of mouse movement
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub resolution_scale: f64, // The view is rendered at this fraction of the window size
+    pub render_width: u32, // Fixed size to render the view at, overriding the scale
+    pub render_height: u32,
+    pub fps_cap: u32, // 0 for no cap beyond vsync
+    pub player_radius: f64, // Size of the player for collisions, in tiles
+}
+
+pub const MIN_FOV: f64 = 30.0;
+pub const MAX_FOV: f64 = 150.0;
+
+// Past half a tile the player can't fit down a corridor
+pub const MIN_RADIUS: f64 = 0.05;
+pub const MAX_RADIUS: f64 = 0.45;
+
+impl Settings {
+    /// The same settings pulled back into sensible ranges.
+    pub fn clamped(&self) -> Settings {
+        Settings {
+            fov: self.fov.max(MIN_FOV).min(MAX_FOV),
+            mouse_sensitivity: self.mouse_sensitivity.max(0.0),
+            resolution_scale: self.resolution_scale.max(0.1).min(1.0),
+            player_radius: self.player_radius.max(MIN_RADIUS).min(MAX_RADIUS),
+            ..self.clone()
+        }
+    }
+
+    /// Size to render the view at for a window of `width` by `height`.
+    pub fn render_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.render_width > 0 && self.render_height > 0 {
+            return (self.render_width, self.render_height);
+        }
+
+        let scale = |n: u32| ((n as f64 * self.resolution_scale).round() as u32).max(1);
+        (scale(width), scale(height))
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fov: 75.0,
+            mouse_sensitivity: 0.5,
+            invert_x: false,
+            invert_y: false,
+            resolution_scale: 1.0,
+            render_width: 0,
+            render_height: 0,
+            fps_cap: 0,
+            player_radius: player::RADIUS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamped_settings() {
+        let settings = Settings {
+            fov: 400.0,
+            resolution_scale: 0.0,
+            player_radius: 1.0,
+            ..Settings::default()
+        };
+
+        let clamped = settings.clamped();
+        assert_eq!(clamped.fov, MAX_FOV);
+        assert_eq!(clamped.player_radius, MAX_RADIUS);
+        assert_eq!(clamped.render_size(800, 600), (80, 60));
+        assert_eq!(Settings::default().clamped(), Settings::default());
+
+        let fixed = Settings {
+            render_width: 320,
+            render_height: 200,
+            ..clamped
+        };
+        assert_eq!(fixed.render_size(1920, 1080), (320, 200));
+    }
+}
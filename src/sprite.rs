@@ -0,0 +1,18 @@
+/// Something standing in the dungeon that is drawn as a camera-facing
+/// billboard: loot, NPCs, monsters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub x: f64,
+    pub y: f64,
+    pub texture: usize,
+}
+
+impl Sprite {
+    pub fn new(x: f64, y: f64, texture: usize) -> Self {
+        Sprite {
+            x: x,
+            y: y,
+            texture: texture,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A file in the temp directory for tests that save and load. The name is
+/// unique to the process, and the file is deleted when this goes out of
+/// scope, even if the test panics.
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(name: &str) -> Self {
+        TempFile {
+            path: env::temp_dir().join(format!("mount_arelle_{}_{}", process::id(), name)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,172 @@
+use picto::read;
+use picto::buffer::Rgba as RgbaImage;
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+lazy_static! {
+    static ref BUILTIN: Arc<Textures> = Arc::new(Textures::from_images(builtin_images()));
+}
+
+/// Every texture the game can draw, addressed by a numeric id.
+///
+/// Textures are named after their file stem (`floor_stone.png` is
+/// `"floor_stone"`), and the name is the stable key: save names, not ids.
+/// The builtin textures take the first ids, in name order. Textures loaded
+/// from a directory come after them, so they never shift a builtin's id,
+/// and a file that replaces a builtin keeps its id.
+#[derive(Debug)]
+pub struct Textures {
+    names: Vec<String>, // Indexed by id
+    images: Vec<RgbaImage>,
+    ids: BTreeMap<String, usize>,
+}
+
+impl Textures {
+    /// The textures from `resources/` that are compiled into the binary.
+    pub fn builtin() -> Arc<Textures> {
+        BUILTIN.clone()
+    }
+
+    /// The builtin textures plus every PNG in `dir`. Files in `dir` replace
+    /// builtin textures with the same name.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Textures> {
+        let mut images = BTreeMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("png")) {
+                continue;
+            }
+
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let image = read::from_path(&path).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {:?}", path.display(), err),
+                )
+            })?;
+
+            images.insert(name, image);
+        }
+
+        Ok(BUILTIN.with_images(images))
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| name.as_str())
+    }
+
+    pub fn get(&self, id: usize) -> Option<&RgbaImage> {
+        self.images.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    fn from_images(images: BTreeMap<String, RgbaImage>) -> Self {
+        let empty = Textures {
+            names: Vec::new(),
+            images: Vec::new(),
+            ids: BTreeMap::new(),
+        };
+
+        empty.with_images(images)
+    }
+
+    // A copy with `images` added after the existing textures, or in place
+    // of the ones they share a name with.
+    fn with_images(&self, images: BTreeMap<String, RgbaImage>) -> Self {
+        let mut textures = Textures {
+            names: self.names.clone(),
+            images: self.images.clone(),
+            ids: self.ids.clone(),
+        };
+
+        for (name, image) in images {
+            if let Some(&id) = textures.ids.get(&name) {
+                textures.images[id] = image;
+                continue;
+            }
+
+            textures.ids.insert(name.clone(), textures.names.len());
+            textures.names.push(name);
+            textures.images.push(image);
+        }
+
+        textures
+    }
+}
+
+fn builtin_images() -> BTreeMap<String, RgbaImage> {
+    let files: Vec<(&str, &[u8])> = vec![
+        ("ceiling_rock", include_bytes!("../resources/ceiling_rock.png")),
+        ("door_wood", include_bytes!("../resources/door_wood.png")),
+        ("floor_dirt", include_bytes!("../resources/floor_dirt.png")),
+        ("floor_stone", include_bytes!("../resources/floor_stone.png")),
+        ("sprite_chest", include_bytes!("../resources/sprite_chest.png")),
+        ("sprite_peddler", include_bytes!("../resources/sprite_peddler.png")),
+        ("sprite_slime", include_bytes!("../resources/sprite_slime.png")),
+        ("wall_brick", include_bytes!("../resources/wall_brick.png")),
+        ("wall_mossy", include_bytes!("../resources/wall_mossy.png")),
+        ("wall_rock", include_bytes!("../resources/wall_rock.png")),
+    ];
+
+    files.into_iter()
+        .map(|(name, bytes)| (name.to_string(), read::from_memory(bytes.to_vec()).unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_looked_up_by_name() {
+        let textures = Textures::builtin();
+        assert_eq!(textures.id("no_such_texture"), None);
+
+        let names = ["ceiling_rock", "door_wood", "floor_stone", "sprite_chest", "wall_rock"];
+        for name in &names {
+            let id = textures.id(name).unwrap();
+            assert_eq!(textures.name(id), Some(*name));
+            assert!(textures.get(id).is_some());
+        }
+    }
+
+    #[test]
+    fn added_textures_keep_the_builtin_ids() {
+        let builtin = Textures::builtin();
+        let image = || RgbaImage::new(2, 2);
+
+        let mut images = BTreeMap::new();
+        images.insert("aaa_first_by_name".to_string(), image());
+        images.insert("wall_rock".to_string(), image());
+        let textures = builtin.with_images(images);
+
+        for id in 0..builtin.len() {
+            assert_eq!(textures.name(id), builtin.name(id));
+        }
+        assert_eq!(textures.id("aaa_first_by_name"), Some(builtin.len()));
+        assert_eq!(textures.len(), builtin.len() + 1);
+
+        let wall = builtin.id("wall_rock").unwrap();
+        assert_eq!(textures.get(wall).unwrap().dimensions(), (2, 2));
+    }
+}